@@ -0,0 +1,553 @@
+//! Detects whether a terminal uses a light or dark background
+//!
+//! _Note: This module is only available with the `detect-background` feature_
+//!
+//! The terminal is asked for its background color with an `OSC 11` query which most modern
+//! terminal emulators answer. Terminals that don't are detected by following the query with a
+//! _primary device attributes_ request that practically every terminal answers, so unsupported
+//! terminals fail quickly instead of waiting on the full timeout. If the terminal can't be queried
+//! then the `COLORFGBG` environment variable set by some terminals (e.g. rxvt and Konsole) is used
+//! as a fallback
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use two_face::theme::{Appearance, EmbeddedThemeName};
+//!
+//! let theme_name = match two_face::background::detect(Duration::from_millis(100)) {
+//!     Some(Appearance::Light) => EmbeddedThemeName::MonokaiExtendedLight,
+//!     Some(Appearance::Dark) | None => EmbeddedThemeName::MonokaiExtended,
+//! };
+//! ```
+
+use std::{env, fmt, io, time::Duration};
+
+use syntect::highlighting::Color;
+
+use crate::theme::Appearance;
+
+/// Everything that can go wrong when querying a terminal's background color
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The file descriptor isn't connected to a terminal
+    NotATerminal,
+    /// The terminal answered, but doesn't support reporting its background color
+    Unsupported,
+    /// The terminal didn't answer before the timeout
+    Timeout,
+    /// The terminal answered with something that we couldn't parse
+    InvalidResponse,
+    /// Reading from or writing to the terminal failed
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotATerminal => f.write_str("not a terminal"),
+            Self::Unsupported => f.write_str("terminal doesn't report its background color"),
+            Self::Timeout => f.write_str("timed out waiting on the terminal to respond"),
+            Self::InvalidResponse => f.write_str("invalid response from the terminal"),
+            Self::Io(err) => write!(f, "terminal io failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Detects the appearance of the controlling terminal
+///
+/// This queries `/dev/tty` waiting at most `timeout` for a response before falling back to
+/// [`from_colorfgbg()`]. Returns [`None`] when neither method works
+pub fn detect(timeout: Duration) -> Option<Appearance> {
+    #[cfg(unix)]
+    {
+        let tty = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty");
+        if let Ok(tty) = tty {
+            if let Ok(color) = query(&tty, timeout) {
+                return Some(Appearance::from_background(color));
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = timeout;
+
+    from_colorfgbg()
+}
+
+/// Queries the background color of the terminal connected to `tty`
+///
+/// The terminal is temporarily switched to non-canonical mode with echoing disabled while
+/// waiting on the response, and the original settings are restored before returning
+///
+/// _Note: Only available on unix platforms_
+#[cfg(unix)]
+pub fn query<T: std::os::fd::AsFd>(tty: &T, timeout: Duration) -> Result<Color, Error> {
+    use std::os::fd::AsRawFd;
+
+    let fd = tty.as_fd().as_raw_fd();
+    // SAFETY: `fd` is a valid file descriptor for as long as `tty` is borrowed
+    if unsafe { libc::isatty(fd) } != 1 {
+        return Err(Error::NotATerminal);
+    }
+
+    let _guard = unix::RawModeGuard::new(fd)?;
+    unix::write_all(fd, QUERY)?;
+    let response = unix::read_response(fd, timeout)?;
+    match response {
+        Response::Background(bytes) => parse_osc11(&bytes).ok_or(Error::InvalidResponse),
+        Response::DeviceAttributes => Err(Error::Unsupported),
+    }
+}
+
+/// Determines the terminal's appearance from the `COLORFGBG` environment variable
+///
+/// See [`parse_colorfgbg()`] for the accepted format
+pub fn from_colorfgbg() -> Option<Appearance> {
+    let value = env::var("COLORFGBG").ok()?;
+    parse_colorfgbg(&value)
+}
+
+/// Parses the value of a `COLORFGBG` environment variable
+///
+/// The value is made up of `;` separated palette indices where the last one is the background
+/// (e.g. `15;0` or `0;default;15`). The background is considered light for palette indices `7`
+/// and `9` through `15`
+///
+/// ```
+/// use two_face::{background::parse_colorfgbg, theme::Appearance};
+///
+/// assert_eq!(parse_colorfgbg("15;0"), Some(Appearance::Dark));
+/// assert_eq!(parse_colorfgbg("0;default;15"), Some(Appearance::Light));
+/// assert_eq!(parse_colorfgbg("default;default"), None);
+/// ```
+pub fn parse_colorfgbg(value: &str) -> Option<Appearance> {
+    let bg: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+    match bg {
+        7 | 9..=15 => Some(Appearance::Light),
+        0..=6 | 8 => Some(Appearance::Dark),
+        _ => None,
+    }
+}
+
+/// The `OSC 11` background query followed by a primary device attributes request
+const QUERY: &[u8] = b"\x1b]11;?\x1b\\\x1b[c";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Response {
+    Background(Vec<u8>),
+    DeviceAttributes,
+}
+
+/// Checks if `buf` holds a complete response
+///
+/// Terminals reply to the queries in the order that they were sent, so seeing the device
+/// attributes reply first means that the background query went unanswered. Otherwise the
+/// background reply only counts once the device attributes reply that follows it has also come in,
+/// so that it doesn't get left behind as stray input. Replies can be preceded by other input like
+/// arrow keys that were pressed while waiting, so any unrelated escape sequences get skipped over
+fn find_response(buf: &[u8]) -> Option<Response> {
+    let device_attributes = escape_starts(buf).find(|&start| {
+        buf[start..].strip_prefix(b"\x1b[?").is_some_and(|rest| {
+            let params_end = rest.iter().position(|b| !matches!(b, b'0'..=b'9' | b';'));
+            params_end.is_some_and(|end| rest[end] == b'c')
+        })
+    })?;
+    Some(match find_background(&buf[..device_attributes]) {
+        Some(background) => Response::Background(background),
+        None => Response::DeviceAttributes,
+    })
+}
+
+/// Finds the first complete `OSC 11` reply in `buf`
+fn find_background(buf: &[u8]) -> Option<Vec<u8>> {
+    escape_starts(buf).find_map(|start| {
+        let candidate = &buf[start..];
+        let rest = candidate.strip_prefix(b"\x1b]11;")?;
+        // OSC replies are terminated by either BEL or ST (`ESC \`)
+        let st = rest.windows(2).position(|w| w == b"\x1b\\").map(|i| (i, 2));
+        let bel = rest.iter().position(|&b| b == 0x07).map(|i| (i, 1));
+        let (end, terminator_len) = st.into_iter().chain(bel).min()?;
+        Some(candidate[..5 + end + terminator_len].to_owned())
+    })
+}
+
+fn escape_starts(buf: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    buf.iter()
+        .enumerate()
+        .filter(|&(_, &b)| b == 0x1b)
+        .map(|(start, _)| start)
+}
+
+/// Parses a reply like `ESC ] 11 ; rgb:RRRR/GGGG/BBBB ST`
+///
+/// Each channel can have between one and four hex digits and gets scaled down to eight bits
+fn parse_osc11(response: &[u8]) -> Option<Color> {
+    let response = std::str::from_utf8(response).ok()?;
+    let body = response.strip_prefix("\x1b]11;")?;
+    let body = body
+        .strip_suffix("\x1b\\")
+        .or_else(|| body.strip_suffix('\x07'))?;
+    let channels = body.strip_prefix("rgb:")?;
+
+    let mut parsed = [0; 3];
+    let mut parts = channels.split('/');
+    for channel in &mut parsed {
+        *channel = parse_channel(parts.next()?)?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let [r, g, b] = parsed;
+    Some(Color { r, g, b, a: 0xff })
+}
+
+fn parse_channel(hex: &str) -> Option<u8> {
+    if hex.is_empty() || hex.len() > 4 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let max = (1u32 << (4 * hex.len())) - 1;
+    // Round to the nearest 8-bit value
+    Some(((value * 0xff + max / 2) / max) as u8)
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        io,
+        os::fd::RawFd,
+        time::{Duration, Instant},
+    };
+
+    use super::{find_background, find_response, Error, Response};
+
+    /// Puts the terminal into non-canonical mode without echo, restoring it when dropped
+    pub(super) struct RawModeGuard {
+        fd: RawFd,
+        original: libc::termios,
+    }
+
+    impl RawModeGuard {
+        pub(super) fn new(fd: RawFd) -> io::Result<Self> {
+            // SAFETY: `termios` is plain old data that gets filled in by `tcgetattr()`
+            let mut original: libc::termios = unsafe { std::mem::zeroed() };
+            // SAFETY: the caller guarantees that `fd` is valid
+            if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            // SAFETY: ^^
+            if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Self { fd, original })
+        }
+    }
+
+    impl Drop for RawModeGuard {
+        fn drop(&mut self) {
+            // SAFETY: `fd` outlives the guard. Nothing reasonable to do if restoring fails
+            unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.original) };
+        }
+    }
+
+    pub(super) fn write_all(fd: RawFd, mut bytes: &[u8]) -> io::Result<()> {
+        while !bytes.is_empty() {
+            // SAFETY: `bytes` is valid for `bytes.len()` bytes
+            let written = unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) };
+            if written < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            bytes = &bytes[written as usize..];
+        }
+        Ok(())
+    }
+
+    pub(super) fn read_response(fd: RawFd, timeout: Duration) -> Result<Response, Error> {
+        let deadline = Instant::now() + timeout;
+        let mut buf = Vec::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                // A terminal that answered the background query is still worth trusting even if
+                // the device attributes reply went missing
+                return find_background(&buf)
+                    .map(Response::Background)
+                    .ok_or(Error::Timeout);
+            }
+
+            let mut poll_fd = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let millis = remaining.as_millis().clamp(1, libc::c_int::MAX as u128) as libc::c_int;
+            // SAFETY: we pass a single valid `pollfd`
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, millis) };
+            if ready < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err.into());
+            } else if ready == 0 {
+                continue;
+            }
+
+            let mut chunk = [0; 256];
+            // SAFETY: `chunk` is valid for `chunk.len()` bytes
+            let read = unsafe { libc::read(fd, chunk.as_mut_ptr().cast(), chunk.len()) };
+            if read < 0 {
+                let err = io::Error::last_os_error();
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+                ) {
+                    continue;
+                }
+                return Err(err.into());
+            } else if read == 0 {
+                // The terminal hung up, so nothing else is coming
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            buf.extend_from_slice(&chunk[..read as usize]);
+
+            if let Some(response) = find_response(&buf) {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osc11_parsing() {
+        let white = Color::WHITE;
+        let black = Color::BLACK;
+        let nord = Color {
+            r: 0x2e,
+            g: 0x34,
+            b: 0x40,
+            a: 0xff,
+        };
+
+        assert_eq!(
+            parse_osc11(b"\x1b]11;rgb:ffff/ffff/ffff\x1b\\"),
+            Some(white)
+        );
+        assert_eq!(parse_osc11(b"\x1b]11;rgb:0000/0000/0000\x07"), Some(black));
+        assert_eq!(parse_osc11(b"\x1b]11;rgb:2e2e/3434/4040\x1b\\"), Some(nord));
+        assert_eq!(parse_osc11(b"\x1b]11;rgb:2e/34/40\x07"), Some(nord));
+        assert_eq!(parse_osc11(b"\x1b]11;rgb:f/f/f\x07"), Some(white));
+
+        assert_eq!(parse_osc11(b"\x1b]11;rgb:ffff/ffff\x07"), None);
+        assert_eq!(parse_osc11(b"\x1b]11;rgb:ffff/ffff/ffff/ffff\x07"), None);
+        assert_eq!(parse_osc11(b"\x1b]11;rgb:fffff/0/0\x07"), None);
+        assert_eq!(parse_osc11(b"\x1b]11;#ffffff\x07"), None);
+        assert_eq!(parse_osc11(b"\x1b]10;rgb:ffff/ffff/ffff\x07"), None);
+    }
+
+    #[test]
+    fn response_framing() {
+        assert_eq!(find_response(b""), None);
+        assert_eq!(find_response(b"\x1b]11;rgb:ffff/ff"), None);
+        // The device attributes reply has to come in too
+        assert_eq!(find_response(b"\x1b]11;rgb:0/0/0\x07"), None);
+        assert_eq!(find_response(b"\x1b]11;rgb:0/0/0\x07\x1b[?62"), None);
+        assert_eq!(
+            find_response(b"\x1b]11;rgb:0/0/0\x07\x1b[?62;c"),
+            Some(Response::Background(b"\x1b]11;rgb:0/0/0\x07".to_vec()))
+        );
+        assert_eq!(
+            find_response(b"\x1b[?1;2c"),
+            Some(Response::DeviceAttributes)
+        );
+        assert_eq!(find_response(b"\x1b[?1;2"), None);
+        // Input that came in before the replies gets skipped
+        assert_eq!(
+            find_response(b"\x1b[A\x1bOB\x1b]11;rgb:0/0/0\x1b\\\x1b[?62c"),
+            Some(Response::Background(b"\x1b]11;rgb:0/0/0\x1b\\".to_vec()))
+        );
+        assert_eq!(
+            find_response(b"\x1b[1;5D\x1b[?62;c"),
+            Some(Response::DeviceAttributes)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    mod pty {
+        use std::{
+            fs::File,
+            io::{Read, Write},
+            os::fd::{FromRawFd, OwnedFd},
+            thread,
+            time::Duration,
+        };
+
+        use super::super::*;
+
+        /// Opens a pseudo-terminal returning the `(controller, terminal)` ends
+        fn open_pty() -> (File, File) {
+            let mut controller = 0;
+            let mut terminal = 0;
+            // SAFETY: all of the optional arguments are null and we get back two fresh fds
+            let ret = unsafe {
+                libc::openpty(
+                    &mut controller,
+                    &mut terminal,
+                    std::ptr::null_mut(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                )
+            };
+            assert_eq!(ret, 0, "{}", io::Error::last_os_error());
+            // SAFETY: `openpty()` just handed us ownership of both fds
+            unsafe {
+                (
+                    OwnedFd::from_raw_fd(controller).into(),
+                    OwnedFd::from_raw_fd(terminal).into(),
+                )
+            }
+        }
+
+        /// Stands in for a terminal emulator by answering the query with each of `replies`
+        ///
+        /// The replies are written separately with a short pause in between, so they don't all get
+        /// picked up by a single read
+        fn respond_with(
+            mut controller: File,
+            replies: &'static [&'static [u8]],
+        ) -> thread::JoinHandle<()> {
+            thread::spawn(move || {
+                let mut seen = Vec::new();
+                let mut buf = [0; 64];
+                while !seen.ends_with(b"\x1b[c") {
+                    let read = controller.read(&mut buf).unwrap();
+                    seen.extend_from_slice(&buf[..read]);
+                }
+                for reply in replies {
+                    controller.write_all(reply).unwrap();
+                    thread::sleep(Duration::from_millis(50));
+                }
+                // Keep the controller open long enough for the reply to be read
+                thread::sleep(Duration::from_millis(200));
+            })
+        }
+
+        /// Returns whatever input is still waiting to be read from `terminal`
+        fn unread(terminal: &File) -> Vec<u8> {
+            use std::os::fd::AsRawFd;
+
+            let fd = terminal.as_raw_fd();
+            let _guard = unix::RawModeGuard::new(fd).unwrap();
+            let mut unread = Vec::new();
+            let mut buf = [0; 64];
+            loop {
+                // SAFETY: `buf` is valid for `buf.len()` bytes
+                let read = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+                if read <= 0 {
+                    break unread;
+                }
+                unread.extend_from_slice(&buf[..read as usize]);
+            }
+        }
+
+        #[test]
+        fn dark_background() {
+            let (controller, terminal) = open_pty();
+            let responder =
+                respond_with(controller, &[b"\x1b]11;rgb:2e2e/3434/4040\x1b\\\x1b[?62c"]);
+            let color = query(&terminal, Duration::from_secs(5)).unwrap();
+            assert_eq!((color.r, color.g, color.b), (0x2e, 0x34, 0x40));
+            assert_eq!(Appearance::from_background(color), Appearance::Dark);
+            responder.join().unwrap();
+        }
+
+        #[test]
+        fn light_background_bel_terminated() {
+            let (controller, terminal) = open_pty();
+            let responder = respond_with(controller, &[b"\x1b]11;rgb:fdfd/f6f6/e3e3\x07\x1b[?62c"]);
+            let color = query(&terminal, Duration::from_secs(5)).unwrap();
+            assert_eq!(Appearance::from_background(color), Appearance::Light);
+            responder.join().unwrap();
+        }
+
+        #[test]
+        fn consumes_every_reply() {
+            let (controller, terminal) = open_pty();
+            let replies: &[&[u8]] = &[b"\x1b]11;rgb:0/0/0\x07", b"\x1b[?62c"];
+            let responder = respond_with(controller, replies);
+            let color = query(&terminal, Duration::from_secs(5)).unwrap();
+            assert_eq!(Appearance::from_background(color), Appearance::Dark);
+            // Give any reply that we didn't wait on a chance to show up
+            thread::sleep(Duration::from_millis(100));
+            assert_eq!(unread(&terminal), b"");
+            responder.join().unwrap();
+        }
+
+        #[test]
+        fn unsupported() {
+            let (controller, terminal) = open_pty();
+            let responder = respond_with(controller, &[b"\x1b[?1;2c"]);
+            let err = query(&terminal, Duration::from_secs(5)).unwrap_err();
+            assert!(matches!(err, Error::Unsupported), "{err:?}");
+            responder.join().unwrap();
+        }
+
+        #[test]
+        fn timeout() {
+            let (_controller, terminal) = open_pty();
+            let err = query(&terminal, Duration::from_millis(50)).unwrap_err();
+            assert!(matches!(err, Error::Timeout), "{err:?}");
+        }
+
+        #[test]
+        fn hangup() {
+            let (controller, terminal) = open_pty();
+            let responder = respond_with(controller, &[]);
+            let err = query(&terminal, Duration::from_secs(5)).unwrap_err();
+            assert!(matches!(err, Error::Io(_)), "{err:?}");
+            responder.join().unwrap();
+        }
+
+        #[test]
+        fn not_a_terminal() {
+            let file = File::open("Cargo.toml").unwrap();
+            let err = query(&file, Duration::from_millis(50)).unwrap_err();
+            assert!(matches!(err, Error::NotATerminal), "{err:?}");
+        }
+    }
+}
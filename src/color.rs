@@ -0,0 +1,193 @@
+//! Internal color math shared by the theme and rendering helpers
+
+use std::sync::OnceLock;
+
+use syntect::highlighting::Color;
+
+/// Converts a single sRGB channel to linear light
+fn linearize(channel: u8) -> f32 {
+    let c = f32::from(channel) / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The relative luminance (`Y`) of a color ranging from `0.0` to `1.0`
+pub(crate) fn luminance(color: Color) -> f32 {
+    0.2126 * linearize(color.r) + 0.7152 * linearize(color.g) + 0.0722 * linearize(color.b)
+}
+
+/// The perceived lightness (CIELAB `L*`) of a color ranging from `0.0` to `100.0`
+pub(crate) fn lightness(color: Color) -> f32 {
+    let y = luminance(color);
+    if y <= 216.0 / 24_389.0 {
+        y * 24_389.0 / 27.0
+    } else {
+        116.0 * y.cbrt() - 16.0
+    }
+}
+
+/// Converts a color to the OKLab color space where euclidean distance roughly matches how
+/// different two colors look
+pub(crate) fn oklab(color: Color) -> [f32; 3] {
+    let [r, g, b] = [color.r, color.g, color.b].map(linearize);
+
+    let l = 0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b;
+    let m = 0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b;
+    let s = 0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b;
+    let [l, m, s] = [l, m, s].map(f32::cbrt);
+
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+/// Converts a color back from the OKLab color space, clamping it to the sRGB gamut
+pub(crate) fn from_oklab([l, a, b]: [f32; 3], alpha: u8) -> Color {
+    let l_ = l + 0.396_337_78 * a + 0.215_803_76 * b;
+    let m_ = l - 0.105_561_346 * a - 0.063_854_17 * b;
+    let s_ = l - 0.089_484_18 * a - 1.291_485_5 * b;
+    let [l, m, s] = [l_, m_, s_].map(|c| c * c * c);
+
+    let [r, g, b] = [
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+        -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    ]
+    .map(delinearize);
+    Color { r, g, b, a: alpha }
+}
+
+/// Converts a linear light channel back to sRGB
+fn delinearize(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+fn distance_sq(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// xterm's default colors for the first 16 palette indices
+pub(crate) const XTERM_BASIC: [Color; 16] = [
+    rgb(0x00, 0x00, 0x00),
+    rgb(0xcd, 0x00, 0x00),
+    rgb(0x00, 0xcd, 0x00),
+    rgb(0xcd, 0xcd, 0x00),
+    rgb(0x00, 0x00, 0xee),
+    rgb(0xcd, 0x00, 0xcd),
+    rgb(0x00, 0xcd, 0xcd),
+    rgb(0xe5, 0xe5, 0xe5),
+    rgb(0x7f, 0x7f, 0x7f),
+    rgb(0xff, 0x00, 0x00),
+    rgb(0x00, 0xff, 0x00),
+    rgb(0xff, 0xff, 0x00),
+    rgb(0x5c, 0x5c, 0xff),
+    rgb(0xff, 0x00, 0xff),
+    rgb(0x00, 0xff, 0xff),
+    rgb(0xff, 0xff, 0xff),
+];
+
+pub(crate) const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color { r, g, b, a: 0xff }
+}
+
+/// Composites a translucent color over an opaque background
+pub(crate) fn blend(color: Color, background: Color) -> Color {
+    let alpha = u16::from(color.a);
+    let mix = |fg: u8, bg: u8| {
+        let mixed = (u16::from(fg) * alpha + u16::from(bg) * (0xff - alpha) + 0x7f) / 0xff;
+        mixed as u8
+    };
+    rgb(
+        mix(color.r, background.r),
+        mix(color.g, background.g),
+        mix(color.b, background.b),
+    )
+}
+
+/// The RGB value of an xterm palette index using xterm's default colors for the first 16
+pub(crate) fn xterm_rgb(index: u8) -> Color {
+    const CUBE_LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+    let (r, g, b) = match index {
+        0..=15 => return XTERM_BASIC[usize::from(index)],
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[usize::from(i / 36)],
+                CUBE_LEVELS[usize::from(i / 6 % 6)],
+                CUBE_LEVELS[usize::from(i % 6)],
+            )
+        }
+        232..=255 => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    };
+    Color { r, g, b, a: 0xff }
+}
+
+fn xterm_oklab(index: u8) -> [f32; 3] {
+    static PALETTE: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+    let palette = PALETTE.get_or_init(|| (0..=255).map(|i| oklab(xterm_rgb(i))).collect());
+    palette[usize::from(index)]
+}
+
+/// Finds the perceptually closest color in the xterm 256-color palette
+///
+/// The first 16 colors are skipped since they're configurable by the user
+pub(crate) fn nearest_ansi256(color: Color) -> u8 {
+    let target = oklab(color);
+    (16..=255)
+        .min_by(|&a, &b| {
+            let a = distance_sq(target, xterm_oklab(a));
+            let b = distance_sq(target, xterm_oklab(b));
+            a.total_cmp(&b)
+        })
+        .expect("Range is non-empty")
+}
+
+/// Finds the closest of the basic 16 colors assuming xterm's defaults
+///
+/// The basic colors are so saturated that the closest color by distance is almost always a gray,
+/// so instead we match on hue for any noticeably colorful color and then pick between the normal
+/// and bright variant based on lightness
+pub(crate) fn nearest_ansi16(color: Color) -> u8 {
+    let [lightness, a, b] = oklab(color);
+    if a.hypot(b) < 0.05 {
+        return match lightness {
+            l if l < 0.45 => 0,
+            l if l < 0.75 => 8,
+            l if l < 0.95 => 7,
+            _ => 15,
+        };
+    }
+
+    let hue = b.atan2(a);
+    let hue_distance = |index: u8| {
+        let [_, a, b] = xterm_oklab(index);
+        let diff = (b.atan2(a) - hue).abs();
+        diff.min(std::f32::consts::TAU - diff)
+    };
+    let lightness_distance = |index: u8| (xterm_oklab(index)[0] - lightness).abs();
+
+    let normal = (1..=6)
+        .min_by(|&a, &b| hue_distance(a).total_cmp(&hue_distance(b)))
+        .expect("Range is non-empty");
+    let bright = normal + 8;
+    if lightness_distance(bright) < lightness_distance(normal) {
+        bright
+    } else {
+        normal
+    }
+}
@@ -0,0 +1,434 @@
+//! A serializable token stream for highlighted code
+//!
+//! [`HighlightedDocument`] keeps the text, scope stack, and resolved style for every token, which
+//! makes it a good fit for frontends that do their own rendering. Colors are stored as CSS color
+//! values, so they can be used as-is in the browser (see [`crate::html::AnsiPalette::css()`] for
+//! the palette colors used by the terminal themes)
+//!
+//! # Example
+//!
+//! ```
+//! use two_face::{document::HighlightedDocument, theme::{extra, EmbeddedThemeName}};
+//!
+//! let syn_set = two_face::syntax::extra_newlines();
+//! let theme_set = extra();
+//!
+//! let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+//! let doc = HighlightedDocument::new(
+//!     "fn main() {}\n",
+//!     &syn_set,
+//!     syntax,
+//!     &theme_set[EmbeddedThemeName::Nord],
+//! )
+//! .unwrap();
+//!
+//! let token = &doc.lines[0][0];
+//! assert_eq!(token.text, "fn");
+//! assert_eq!(token.scopes.last().unwrap(), "storage.type.function.rust");
+//! assert_eq!(doc.style(token).unwrap().foreground.as_deref(), Some("#81a1c1"));
+//! ```
+
+use std::collections::HashMap;
+
+use serde_derive::{Deserialize, Serialize};
+use syntect::{
+    easy::ScopeRegionIterator,
+    highlighting::{FontStyle, Highlighter, Style, Theme},
+    parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+    Error,
+};
+
+use crate::{runs::merge_ranges, theme::css::css_color};
+
+/// Highlighted code as lines of tokens
+///
+/// Line endings aren't included in the tokens, so an empty line has no tokens
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighlightedDocument {
+    /// The theme's default text color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<String>,
+    /// The theme's background color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    /// The shared styles referenced by [`TokenStyle::Index`] (only used in compact documents)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub styles: Vec<ResolvedStyle>,
+    /// The tokens for each line
+    pub lines: Vec<Vec<Token>>,
+}
+
+/// A single highlighted piece of text
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Token {
+    /// The text
+    pub text: String,
+    /// The full scope stack from the outermost scope to the innermost
+    pub scopes: Vec<String>,
+    /// The token's style
+    pub style: TokenStyle,
+}
+
+/// Either a token's own style or an index into [`HighlightedDocument::styles`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TokenStyle {
+    /// An index into the document's shared styles
+    Index(usize),
+    /// The style itself
+    Inline(ResolvedStyle),
+}
+
+/// A token's style after resolving it against the theme
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResolvedStyle {
+    /// The text color or `None` for the terminal's default color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<String>,
+    /// The background color or `None` for the terminal's default color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    /// Whether the text is bold
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bold: bool,
+    /// Whether the text is italic
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub italic: bool,
+    /// Whether the text is underlined
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub underline: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+impl From<Style> for ResolvedStyle {
+    fn from(style: Style) -> Self {
+        Self {
+            foreground: css_color(style.foreground),
+            background: css_color(style.background),
+            bold: style.font_style.contains(FontStyle::BOLD),
+            italic: style.font_style.contains(FontStyle::ITALIC),
+            underline: style.font_style.contains(FontStyle::UNDERLINE),
+        }
+    }
+}
+
+impl HighlightedDocument {
+    /// Highlights `s` into a document where every token has its own style
+    pub fn new(
+        s: &str,
+        syntax_set: &SyntaxSet,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+    ) -> Result<Self, Error> {
+        let highlighter = Highlighter::new(theme);
+        let mut styles: HashMap<Vec<Scope>, ResolvedStyle> = HashMap::new();
+        let mut parse_state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(s) {
+            let ops = parse_state.parse_line(line, syntax_set)?;
+            let mut ranges = Vec::new();
+            for (text, op) in ScopeRegionIterator::new(&ops, line) {
+                stack.apply(op)?;
+                ranges.push((stack.as_slice().to_owned(), text));
+            }
+
+            let tokens = merge_ranges(ranges, None)
+                .into_iter()
+                .map(|(scopes, text)| {
+                    let names = scopes.iter().map(|scope| scope.build_string()).collect();
+                    let style = styles
+                        .entry(scopes)
+                        .or_insert_with_key(|scopes| highlighter.style_for_stack(scopes).into());
+                    Token {
+                        text,
+                        scopes: names,
+                        style: TokenStyle::Inline(style.clone()),
+                    }
+                })
+                .collect();
+            lines.push(tokens);
+        }
+
+        Ok(Self {
+            foreground: theme.settings.foreground.and_then(css_color),
+            background: theme.settings.background.and_then(css_color),
+            styles: Vec::new(),
+            lines,
+        })
+    }
+
+    /// Dedupes all of the token styles into [`HighlightedDocument::styles`]
+    ///
+    /// Tokens end up referring to their style by [`TokenStyle::Index`] which cuts down on the size
+    /// of the serialized document considerably
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::{document::{HighlightedDocument, TokenStyle}, theme::{extra, EmbeddedThemeName}};
+    ///
+    /// let syn_set = two_face::syntax::extra_newlines();
+    /// let theme = &extra()[EmbeddedThemeName::Nord];
+    /// let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+    /// let code = "let a = 1;\nlet b = 2;\n";
+    /// let doc = HighlightedDocument::new(code, &syn_set, syntax, theme).unwrap();
+    ///
+    /// let compact = doc.clone().compact();
+    /// assert!(compact.styles.len() < compact.lines.iter().flatten().count());
+    /// let first = &compact.lines[0][0];
+    /// assert_eq!(first.style, TokenStyle::Index(0));
+    /// assert_eq!(compact.style(first), doc.style(&doc.lines[0][0]));
+    /// ```
+    pub fn compact(mut self) -> Self {
+        let mut indices: HashMap<ResolvedStyle, usize> = self
+            .styles
+            .iter()
+            .enumerate()
+            .map(|(index, style)| (style.clone(), index))
+            .collect();
+        for token in self.lines.iter_mut().flatten() {
+            if let TokenStyle::Inline(style) = &token.style {
+                let index = match indices.get(style) {
+                    Some(&index) => index,
+                    None => {
+                        let index = self.styles.len();
+                        self.styles.push(style.clone());
+                        indices.insert(style.clone(), index);
+                        index
+                    }
+                };
+                token.style = TokenStyle::Index(index);
+            }
+        }
+        self
+    }
+
+    /// Looks up the style for `token`
+    ///
+    /// This is only `None` for an index that's out of bounds
+    pub fn style<'doc>(&'doc self, token: &'doc Token) -> Option<&'doc ResolvedStyle> {
+        match &token.style {
+            TokenStyle::Index(index) => self.styles.get(*index),
+            TokenStyle::Inline(style) => Some(style),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use syntect::easy::HighlightLines;
+
+    use crate::theme::{extra, EmbeddedLazyThemeSet, EmbeddedThemeName};
+
+    const CODE: &str = "fn main() {\n\n    let s = \"hi\"; // \u{1f980}\n}\n";
+
+    fn doc(theme: EmbeddedThemeName) -> HighlightedDocument {
+        let syn_set = crate::syntax::extra_newlines();
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        HighlightedDocument::new(CODE, &syn_set, syntax, &extra()[theme]).unwrap()
+    }
+
+    #[test]
+    fn json() {
+        let syn_set = crate::syntax::extra_newlines();
+        let syntax = syn_set.find_syntax_by_extension("toml").unwrap();
+        let theme = &extra()[EmbeddedThemeName::InspiredGithub];
+        let doc = HighlightedDocument::new("a = 1\n", &syn_set, syntax, theme).unwrap();
+        let json = serde_json::to_string_pretty(&doc).unwrap();
+        insta::assert_snapshot!(json, @r##"
+        {
+          "foreground": "#323232",
+          "background": "#ffffff",
+          "lines": [
+            [
+              {
+                "text": "a",
+                "scopes": [
+                  "source.toml",
+                  "meta.tag.key.toml",
+                  "entity.name.tag.toml"
+                ],
+                "style": {
+                  "foreground": "#63a35c",
+                  "background": "#ffffff"
+                }
+              },
+              {
+                "text": " ",
+                "scopes": [
+                  "source.toml"
+                ],
+                "style": {
+                  "foreground": "#323232",
+                  "background": "#ffffff"
+                }
+              },
+              {
+                "text": "=",
+                "scopes": [
+                  "source.toml",
+                  "punctuation.definition.key-value.toml"
+                ],
+                "style": {
+                  "foreground": "#323232",
+                  "background": "#ffffff"
+                }
+              },
+              {
+                "text": " ",
+                "scopes": [
+                  "source.toml"
+                ],
+                "style": {
+                  "foreground": "#323232",
+                  "background": "#ffffff"
+                }
+              },
+              {
+                "text": "1",
+                "scopes": [
+                  "source.toml",
+                  "constant.numeric.integer.toml"
+                ],
+                "style": {
+                  "foreground": "#0086b3",
+                  "background": "#ffffff"
+                }
+              }
+            ]
+          ]
+        }
+        "##);
+        let compact = serde_json::to_string_pretty(&doc.compact()).unwrap();
+        insta::assert_snapshot!(compact, @r##"
+        {
+          "foreground": "#323232",
+          "background": "#ffffff",
+          "styles": [
+            {
+              "foreground": "#63a35c",
+              "background": "#ffffff"
+            },
+            {
+              "foreground": "#323232",
+              "background": "#ffffff"
+            },
+            {
+              "foreground": "#0086b3",
+              "background": "#ffffff"
+            }
+          ],
+          "lines": [
+            [
+              {
+                "text": "a",
+                "scopes": [
+                  "source.toml",
+                  "meta.tag.key.toml",
+                  "entity.name.tag.toml"
+                ],
+                "style": 0
+              },
+              {
+                "text": " ",
+                "scopes": [
+                  "source.toml"
+                ],
+                "style": 1
+              },
+              {
+                "text": "=",
+                "scopes": [
+                  "source.toml",
+                  "punctuation.definition.key-value.toml"
+                ],
+                "style": 1
+              },
+              {
+                "text": " ",
+                "scopes": [
+                  "source.toml"
+                ],
+                "style": 1
+              },
+              {
+                "text": "1",
+                "scopes": [
+                  "source.toml",
+                  "constant.numeric.integer.toml"
+                ],
+                "style": 2
+              }
+            ]
+          ]
+        }
+        "##);
+    }
+
+    #[test]
+    fn palette_colors() {
+        let doc = doc(EmbeddedThemeName::Base16);
+        let compact = serde_json::to_string(&doc.compact().styles).unwrap();
+        insta::assert_snapshot!(compact, @r#"[{"foreground":"var(--ansi-5, #cd00cd)","background":"var(--ansi-0, #000000)"},{"foreground":"var(--ansi-7, #e5e5e5)","background":"var(--ansi-0, #000000)"},{"foreground":"var(--ansi-4, #0000ee)","background":"var(--ansi-0, #000000)"},{"foreground":"var(--ansi-2, #00cd00)","background":"var(--ansi-0, #000000)"},{"foreground":"var(--ansi-8, #7f7f7f)","background":"var(--ansi-0, #000000)"}]"#);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let doc = doc(EmbeddedThemeName::Nord);
+        let json = serde_json::to_string(&doc).unwrap();
+        assert_eq!(
+            serde_json::from_str::<HighlightedDocument>(&json).unwrap(),
+            doc
+        );
+
+        let compact = doc.clone().compact();
+        let json = serde_json::to_string(&compact).unwrap();
+        assert_eq!(
+            serde_json::from_str::<HighlightedDocument>(&json).unwrap(),
+            compact
+        );
+        assert_eq!(compact.clone().compact(), compact);
+    }
+
+    #[test]
+    fn matches_highlighter_for_every_embedded_theme() {
+        let syn_set = crate::syntax::extra_newlines();
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        let theme_set = extra();
+        for &name in EmbeddedLazyThemeSet::theme_names() {
+            let theme = &theme_set[name];
+            let doc = HighlightedDocument::new(CODE, &syn_set, syntax, theme)
+                .unwrap()
+                .compact();
+
+            let mut highlighter = HighlightLines::new(syntax, theme);
+            for (line, tokens) in LinesWithEndings::from(CODE).zip(&doc.lines) {
+                let text: String = tokens.iter().map(|token| token.text.as_str()).collect();
+                assert_eq!(text, line.trim_end_matches('\n'));
+
+                // Compare the styles char by char since tokens get split differently
+                let expected = highlighter
+                    .highlight_line(line, &syn_set)
+                    .unwrap()
+                    .into_iter()
+                    .flat_map(|(style, text)| {
+                        text.trim_end_matches('\n')
+                            .chars()
+                            .map(move |_| ResolvedStyle::from(style))
+                    });
+                let actual = tokens.iter().flat_map(|token| {
+                    let style = doc.style(token).unwrap();
+                    token.text.chars().map(move |_| style.clone())
+                });
+                assert!(expected.eq(actual), "{name:?}: {line:?}");
+            }
+        }
+    }
+}
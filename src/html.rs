@@ -0,0 +1,847 @@
+//! Configurable HTML output for highlighted code
+//!
+//! The [`Renderer`] can either style the code with inline `style` attributes from a [`Theme`] or
+//! with classes meant to pair with the stylesheets from [`crate::theme`]. Class-based output
+//! doesn't depend on any theme, so the same HTML can be styled by [`crate::theme::css()`],
+//! [`crate::theme::css_all_themes()`], or [`crate::theme::dual_css()`]
+//!
+//! The rendered code is wrapped in `<pre class="code"><code>` (with the class prefixed according
+//! to the [`ClassStyle`]) and each line (including its line ending) in a
+//! `<span class="code-line" data-line="N">`. Highlighted lines are displayed as blocks so that the
+//! highlight spans the whole width of the code
+//!
+//! The palette colors used by the terminal themes (see [`crate::terminal`]) can't be expressed as
+//! inline styles, so they become `ansi-fg-<index>` and `ansi-bg-<index>` classes instead. Those
+//! are styled by [`AnsiPalette::css()`]
+
+use std::{fmt, fmt::Write, ops::RangeInclusive, str::FromStr};
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, FontStyle, Style, Theme},
+    html::{line_tokens_to_classed_spans, ClassStyle},
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+    Error,
+};
+
+use crate::{
+    color,
+    terminal::AnsiColor,
+    theme::css::{class_name, css_color},
+};
+
+/// Highlights `s` into class-based HTML using a default [`Renderer`]
+///
+/// # Example
+///
+/// Rendering a code block that follows the reader's light/dark preference
+///
+/// ```
+/// use two_face::{html::classed_html_for_string, theme::{dual_css, EmbeddedThemeName}};
+/// use syntect::html::ClassStyle;
+///
+/// let syn_set = two_face::syntax::extra_newlines();
+/// let syntax = syn_set.find_syntax_by_extension("toml").unwrap();
+/// let style = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+///
+/// let html = classed_html_for_string("key = 123\n", &syn_set, syntax, style).unwrap();
+/// let stylesheet = dual_css(EmbeddedThemeName::Github, EmbeddedThemeName::Nord, style);
+/// let page = format!("<style>\n{stylesheet}</style>\n{html}");
+/// # assert!(html.starts_with("<pre class=\"hl-code\">"));
+/// ```
+pub fn classed_html_for_string(
+    s: &str,
+    syntax_set: &SyntaxSet,
+    syntax: &SyntaxReference,
+    style: ClassStyle,
+) -> Result<String, Error> {
+    Renderer::new().render_classed(s, syntax_set, syntax, style)
+}
+
+/// A set of line numbers like the `{3-5,8}` commonly used to emphasize lines in code blocks
+///
+/// # Example
+///
+/// ```
+/// use two_face::html::LineRanges;
+///
+/// let ranges: LineRanges = "{3-5,8}".parse().unwrap();
+/// assert!(ranges.contains(4));
+/// assert!(!ranges.contains(6));
+/// // The braces are optional
+/// assert_eq!("3-5, 8".parse::<LineRanges>().unwrap(), ranges);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineRanges(Vec<RangeInclusive<usize>>);
+
+impl LineRanges {
+    /// Creates an empty set of line ranges
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an inclusive range of lines
+    pub fn range(mut self, lines: RangeInclusive<usize>) -> Self {
+        self.0.push(lines);
+        self
+    }
+
+    /// Whether `line` falls within any of the ranges
+    pub fn contains(&self, line: usize) -> bool {
+        self.0.iter().any(|range| range.contains(&line))
+    }
+}
+
+impl FromStr for LineRanges {
+    type Err = ParseLineRangesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseLineRangesError(s.to_owned());
+        let trimmed = s.trim();
+        let inner = match trimmed.strip_prefix('{') {
+            Some(rest) => rest.strip_suffix('}').ok_or_else(err)?,
+            None => trimmed,
+        };
+
+        let mut ranges = Self::new();
+        for part in inner
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let parse = |num: &str| num.trim().parse::<usize>().map_err(|_| err());
+            let range = match part.split_once('-') {
+                Some((start, end)) => parse(start)?..=parse(end)?,
+                None => parse(part)?..=parse(part)?,
+            };
+            if range.is_empty() {
+                return Err(err());
+            }
+            ranges = ranges.range(range);
+        }
+        Ok(ranges)
+    }
+}
+
+/// An error from parsing [`LineRanges`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseLineRangesError(String);
+
+impl fmt::Display for ParseLineRangesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid line ranges: `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseLineRangesError {}
+
+/// Renders highlighted code into HTML
+///
+/// # Example
+///
+/// ```
+/// use two_face::{html::Renderer, theme::{extra, EmbeddedThemeName}};
+///
+/// let syn_set = two_face::syntax::extra_newlines();
+/// let theme_set = extra();
+///
+/// let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+/// let renderer = Renderer::new()
+///     .line_numbers(true)
+///     .line_ids("main-")
+///     .highlight_lines("{2}".parse().unwrap())
+///     .caption("main.rs");
+/// let html = renderer
+///     .render(
+///         "fn main() {\n    println!(\"hi\");\n}\n",
+///         &syn_set,
+///         syntax,
+///         &theme_set[EmbeddedThemeName::Nord],
+///     )
+///     .unwrap();
+/// assert!(html.starts_with("<figure>\n<figcaption>main.rs</figcaption>\n"));
+/// assert!(html.contains("<span class=\"code-line code-line-highlighted\" id=\"main-2\""));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    line_numbers: bool,
+    first_line: usize,
+    line_ids: Option<String>,
+    highlighted: LineRanges,
+    caption: Option<String>,
+    palette: Option<AnsiPalette>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            line_numbers: false,
+            first_line: 1,
+            line_ids: None,
+            highlighted: LineRanges::new(),
+            caption: None,
+            palette: None,
+        }
+    }
+}
+
+impl Renderer {
+    /// Creates a renderer with the default settings
+    ///
+    /// By default there are no line numbers, line ids, highlighted lines, or caption
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to display a gutter with line numbers
+    ///
+    /// The numbers can't be selected, so copying the code leaves them out
+    pub fn line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
+        self
+    }
+
+    /// The number of the first line (defaults to 1)
+    pub fn first_line(mut self, number: usize) -> Self {
+        self.first_line = number;
+        self
+    }
+
+    /// Gives each line an `id` of `prefix` followed by the line number to allow for linking to
+    /// lines
+    ///
+    /// The prefix should be unique for each code block on the page
+    pub fn line_ids(mut self, prefix: impl Into<String>) -> Self {
+        self.line_ids = Some(prefix.into());
+        self
+    }
+
+    /// Emphasizes the lines within `lines` using the theme's `line_highlight` color
+    pub fn highlight_lines(mut self, lines: LineRanges) -> Self {
+        self.highlighted = lines;
+        self
+    }
+
+    /// Wraps the code in a `<figure>` with `caption` (typically a file name) as the
+    /// `<figcaption>`
+    pub fn caption(mut self, caption: impl Into<String>) -> Self {
+        self.caption = Some(caption.into());
+        self
+    }
+
+    /// Resolves palette colors through `palette` when rendering with inline styles
+    ///
+    /// By default palette colors (see [`crate::terminal`]) get rendered as classes (e.g.
+    /// `ansi-fg-3`) to be styled by [`AnsiPalette::css()`]. This bakes in the colors instead which
+    /// is handy for previewing the terminal themes
+    pub fn palette(mut self, palette: AnsiPalette) -> Self {
+        self.palette = Some(palette);
+        self
+    }
+
+    /// Renders `s` using inline styles from `theme`
+    pub fn render(
+        &self,
+        s: &str,
+        syntax_set: &SyntaxSet,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+    ) -> Result<String, Error> {
+        let settings = &theme.settings;
+        let palette = self.palette.as_ref();
+        let pre_style = Inline::default()
+            .color(Layer::Foreground, settings.foreground, palette)
+            .color(Layer::Background, settings.background, palette);
+        let gutter_style = Inline::default()
+            .color(Layer::Foreground, settings.gutter_foreground, palette)
+            .color(Layer::Background, settings.gutter, palette)
+            .declaration("user-select:none;");
+        let highlight_style = Inline::default()
+            .color(Layer::Background, settings.line_highlight, palette)
+            .declaration("display:block;");
+        let classes = Classes::new(ClassStyle::Spaced);
+
+        let mut out = String::new();
+        self.open(&mut out, &classes, &pre_style);
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let lines = LinesWithEndings::from(s);
+        let width = self.number_width(s);
+        for (number, line) in (self.first_line..).zip(lines) {
+            let ranges = highlighter.highlight_line(line, syntax_set)?;
+            let highlighted = self.highlighted.contains(number);
+            let no_style = Inline::default();
+            let line_style = if highlighted {
+                &highlight_style
+            } else {
+                &no_style
+            };
+            self.open_line(&mut out, &classes, number, highlighted, line_style);
+            if self.line_numbers {
+                write_line_number(&mut out, &classes, number, width, &gutter_style);
+            }
+            // Matches the background that syntect falls back to
+            let background = settings.background.unwrap_or(Color::WHITE);
+            write_styled_ranges(&mut out, &ranges, background, palette);
+            out.push_str(line_ending(line));
+            out.push_str("</span>");
+        }
+        self.close(&mut out);
+        Ok(out)
+    }
+
+    /// Renders `s` using classes for each scope
+    ///
+    /// Pair this with a stylesheet generated using the same `style`
+    pub fn render_classed(
+        &self,
+        s: &str,
+        syntax_set: &SyntaxSet,
+        syntax: &SyntaxReference,
+        style: ClassStyle,
+    ) -> Result<String, Error> {
+        let classes = Classes::new(style);
+        let mut out = String::new();
+        let no_style = Inline::default();
+        self.open(&mut out, &classes, &no_style);
+        let mut parse_state = ParseState::new(syntax);
+        let mut scope_stack = ScopeStack::new();
+        let width = self.number_width(s);
+        for (number, line) in (self.first_line..).zip(LinesWithEndings::from(s)) {
+            let highlighted = self.highlighted.contains(number);
+            self.open_line(&mut out, &classes, number, highlighted, &no_style);
+            if self.line_numbers {
+                write_line_number(&mut out, &classes, number, width, &no_style);
+            }
+
+            // Each line gets its own closed set of spans, so reopen the spans carried over from
+            // the previous line
+            for scope in scope_stack.as_slice() {
+                out.push_str("<span class=\"");
+                let atoms = scope.build_string();
+                for (i, atom) in atoms.split('.').enumerate() {
+                    if i != 0 {
+                        out.push(' ');
+                    }
+                    push_escaped(&mut out, &class_name(atom, classes.style));
+                }
+                out.push_str("\">");
+            }
+            let content = &line[..line.len() - line_ending(line).len()];
+            let ops = parse_state.parse_line(line, syntax_set)?;
+            // The line ending is left out of the spans, so drop any ops past the content
+            let ops: Vec<_> = ops
+                .into_iter()
+                .map(|(i, op)| (i.min(content.len()), op))
+                .collect();
+            let (html, _) = line_tokens_to_classed_spans(content, &ops, style, &mut scope_stack)?;
+            out.push_str(&html);
+            for _ in scope_stack.as_slice() {
+                out.push_str("</span>");
+            }
+
+            out.push_str(line_ending(line));
+            out.push_str("</span>");
+        }
+        self.close(&mut out);
+        Ok(out)
+    }
+
+    fn number_width(&self, s: &str) -> usize {
+        let last_line = self.first_line + LinesWithEndings::from(s).count().saturating_sub(1);
+        last_line.to_string().len()
+    }
+
+    fn open(&self, out: &mut String, classes: &Classes, pre_style: &Inline) {
+        if let Some(caption) = &self.caption {
+            out.push_str("<figure>\n<figcaption>");
+            push_escaped(out, caption);
+            out.push_str("</figcaption>\n");
+        }
+        out.push_str("<pre");
+        pre_style.write_attrs(out, &classes.code);
+        out.push_str("><code>");
+    }
+
+    fn open_line(
+        &self,
+        out: &mut String,
+        classes: &Classes,
+        number: usize,
+        highlighted: bool,
+        line_style: &Inline,
+    ) {
+        out.push_str("<span");
+        let mut line_classes = classes.line.clone();
+        if highlighted {
+            write!(line_classes, " {}", classes.highlighted).expect("Infallible");
+        }
+        line_style.write_classes(out, &line_classes);
+        if let Some(prefix) = &self.line_ids {
+            out.push_str(" id=\"");
+            push_escaped(out, prefix);
+            write!(out, "{number}\"").expect("Infallible");
+        }
+        write!(out, " data-line=\"{number}\"").expect("Infallible");
+        line_style.write_style(out);
+        out.push('>');
+    }
+
+    fn close(&self, out: &mut String) {
+        out.push_str("</code></pre>\n");
+        if self.caption.is_some() {
+            out.push_str("</figure>\n");
+        }
+    }
+}
+
+/// The class names for the extra elements
+struct Classes {
+    style: ClassStyle,
+    code: String,
+    line: String,
+    line_number: String,
+    highlighted: String,
+}
+
+impl Classes {
+    fn new(style: ClassStyle) -> Self {
+        Self {
+            style,
+            code: class_name("code", style),
+            line: class_name("code-line", style),
+            line_number: class_name("code-line-number", style),
+            highlighted: class_name("code-line-highlighted", style),
+        }
+    }
+}
+
+fn write_line_number(
+    out: &mut String,
+    classes: &Classes,
+    number: usize,
+    width: usize,
+    gutter_style: &Inline,
+) {
+    out.push_str("<span");
+    gutter_style.write_attrs(out, &classes.line_number);
+    write!(out, ">{number:>width$} </span>").expect("Infallible");
+}
+
+/// Writes out the ranges as styled spans merging any neighbors with matching styles
+fn write_styled_ranges(
+    out: &mut String,
+    ranges: &[(Style, &str)],
+    background: Color,
+    palette: Option<&AnsiPalette>,
+) {
+    let mut ranges = ranges.iter().peekable();
+    while let Some(&(style, text)) = ranges.next() {
+        let mut text = text.to_owned();
+        while let Some(&&(next_style, next_text)) = ranges.peek() {
+            if next_style != style {
+                break;
+            }
+            text.push_str(next_text);
+            ranges.next();
+        }
+        let text = &text[..text.len() - line_ending(&text).len()];
+        if text.is_empty() {
+            continue;
+        }
+
+        let mut span_style =
+            Inline::default().color(Layer::Foreground, Some(style.foreground), palette);
+        if style.background != background {
+            span_style = span_style.color(Layer::Background, Some(style.background), palette);
+        }
+        for (flag, declaration) in [
+            (FontStyle::BOLD, "font-weight:bold;"),
+            (FontStyle::ITALIC, "font-style:italic;"),
+            (FontStyle::UNDERLINE, "text-decoration:underline;"),
+        ] {
+            if style.font_style.contains(flag) {
+                span_style = span_style.declaration(declaration);
+            }
+        }
+
+        if span_style.is_empty() {
+            push_escaped(out, text);
+        } else {
+            out.push_str("<span");
+            span_style.write_attrs(out, "");
+            out.push('>');
+            push_escaped(out, text);
+            out.push_str("</span>");
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Layer {
+    Foreground,
+    Background,
+}
+
+/// Inline styling where palette colors either become classes or get resolved through a palette
+#[derive(Default)]
+struct Inline {
+    classes: String,
+    style: String,
+}
+
+impl Inline {
+    fn color(mut self, layer: Layer, color: Option<Color>, palette: Option<&AnsiPalette>) -> Self {
+        let Some(color) = color else {
+            return self;
+        };
+        let (property, class) = match layer {
+            Layer::Foreground => ("color", "ansi-fg"),
+            Layer::Background => ("background-color", "ansi-bg"),
+        };
+        match (AnsiColor::from(color), palette) {
+            (AnsiColor::Default, _) => {}
+            (AnsiColor::Palette(index), None) => {
+                if !self.classes.is_empty() {
+                    self.classes.push(' ');
+                }
+                write!(self.classes, "{class}-{index}").expect("Infallible");
+            }
+            (AnsiColor::Palette(index), Some(palette)) => {
+                let Color { r, g, b, .. } = palette.color(index);
+                write!(self.style, "{property}:#{r:02x}{g:02x}{b:02x};").expect("Infallible");
+            }
+            (AnsiColor::Rgb { .. }, _) => {
+                if let Some(value) = css_color(color) {
+                    write!(self.style, "{property}:{value};").expect("Infallible");
+                }
+            }
+        }
+        self
+    }
+
+    fn declaration(mut self, declaration: &str) -> Self {
+        self.style.push_str(declaration);
+        self
+    }
+
+    fn is_empty(&self) -> bool {
+        self.classes.is_empty() && self.style.is_empty()
+    }
+
+    fn write_attrs(&self, out: &mut String, base_classes: &str) {
+        self.write_classes(out, base_classes);
+        self.write_style(out);
+    }
+
+    fn write_classes(&self, out: &mut String, base_classes: &str) {
+        let classes = [base_classes, &self.classes]
+            .into_iter()
+            .filter(|classes| !classes.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !classes.is_empty() {
+            out.push_str(" class=\"");
+            push_escaped(out, &classes);
+            out.push('"');
+        }
+    }
+
+    fn write_style(&self, out: &mut String) {
+        if !self.style.is_empty() {
+            write!(out, " style=\"{}\"", self.style).expect("Infallible");
+        }
+    }
+}
+
+/// A 16-color palette for displaying the palette colors used by the terminal themes
+///
+/// Only the first 16 colors are configurable. The rest of the 256 colors follow xterm's standard
+/// color cube and gray ramp
+///
+/// # Example
+///
+/// ```
+/// use two_face::html::AnsiPalette;
+///
+/// let stylesheet = AnsiPalette::TANGO.css();
+/// assert!(stylesheet.contains("  --ansi-1: #cc0000;\n"));
+/// assert!(stylesheet.contains(".ansi-fg-1 { color: var(--ansi-1); }\n"));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnsiPalette([Color; 16]);
+
+impl Default for AnsiPalette {
+    fn default() -> Self {
+        Self::XTERM
+    }
+}
+
+impl AnsiPalette {
+    /// xterm's default colors
+    pub const XTERM: Self = Self(color::XTERM_BASIC);
+
+    /// The Tango Desktop Project's colors used by GNOME Terminal among others
+    pub const TANGO: Self = Self([
+        color::rgb(0x2e, 0x34, 0x36),
+        color::rgb(0xcc, 0x00, 0x00),
+        color::rgb(0x4e, 0x9a, 0x06),
+        color::rgb(0xc4, 0xa0, 0x00),
+        color::rgb(0x34, 0x65, 0xa4),
+        color::rgb(0x75, 0x50, 0x7b),
+        color::rgb(0x06, 0x98, 0x9a),
+        color::rgb(0xd3, 0xd7, 0xcf),
+        color::rgb(0x55, 0x57, 0x53),
+        color::rgb(0xef, 0x29, 0x29),
+        color::rgb(0x8a, 0xe2, 0x34),
+        color::rgb(0xfc, 0xe9, 0x4f),
+        color::rgb(0x72, 0x9f, 0xcf),
+        color::rgb(0xad, 0x7f, 0xa8),
+        color::rgb(0x34, 0xe2, 0xe2),
+        color::rgb(0xee, 0xee, 0xec),
+    ]);
+
+    /// A palette from the 16 basic colors
+    pub const fn new(colors: [Color; 16]) -> Self {
+        Self(colors)
+    }
+
+    /// The color for a palette index
+    pub fn color(&self, index: u8) -> Color {
+        match self.0.get(usize::from(index)) {
+            Some(&color) => color,
+            None => color::xterm_rgb(index),
+        }
+    }
+
+    /// Resolves a theme color to an opaque RGB value or `None` for the terminal's default colors
+    ///
+    /// Translucent colors get composited over `background` (or white when there isn't one)
+    pub(crate) fn resolve(&self, color: Color, background: Option<Color>) -> Option<Color> {
+        let color = match AnsiColor::from(color) {
+            AnsiColor::Default => return None,
+            AnsiColor::Palette(index) => return Some(self.color(index)),
+            AnsiColor::Rgb { .. } => color,
+        };
+        let background = background
+            .and_then(|background| self.resolve(background, None))
+            .unwrap_or(color::rgb(0xff, 0xff, 0xff));
+        Some(color::blend(color, background))
+    }
+
+    /// Generates a stylesheet for the palette
+    ///
+    /// This defines a `--ansi-<index>` custom property on `:root` for each of the 256 colors along
+    /// with the `ansi-fg-<index>` and `ansi-bg-<index>` classes used by [`Renderer`]. The
+    /// stylesheets from [`crate::theme::css()`] pick up the custom properties too
+    pub fn css(&self) -> String {
+        let mut css = String::from(":root {\n");
+        for index in 0..=255 {
+            let Color { r, g, b, .. } = self.color(index);
+            writeln!(css, "  --ansi-{index}: #{r:02x}{g:02x}{b:02x};").expect("Infallible");
+        }
+        css.push_str("}\n");
+        for index in 0..=255 {
+            writeln!(css, ".ansi-fg-{index} {{ color: var(--ansi-{index}); }}")
+                .expect("Infallible");
+        }
+        for index in 0..=255 {
+            writeln!(
+                css,
+                ".ansi-bg-{index} {{ background-color: var(--ansi-{index}); }}"
+            )
+            .expect("Infallible");
+        }
+        css
+    }
+}
+
+fn line_ending(line: &str) -> &str {
+    let content = line.trim_end_matches(['\r', '\n']);
+    &line[content.len()..]
+}
+
+/// Escapes `s` for use in either text or attribute values
+pub(crate) fn push_escaped(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::theme::EmbeddedThemeName;
+
+    use strum::IntoEnumIterator;
+
+    const SAMPLE_RUST: &str = "// <hi>\nfn main() {\n    let s = \"\n\";\n}\n";
+
+    fn render(renderer: &Renderer, style: Option<ClassStyle>) -> String {
+        let syn_set = crate::syntax::extra_newlines();
+        let theme_set = crate::theme::extra();
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        match style {
+            Some(style) => renderer.render_classed(SAMPLE_RUST, &syn_set, syntax, style),
+            None => renderer.render(
+                SAMPLE_RUST,
+                &syn_set,
+                syntax,
+                &theme_set[EmbeddedThemeName::Nord],
+            ),
+        }
+        .unwrap()
+    }
+
+    #[test]
+    fn line_ranges() {
+        let ranges: LineRanges = "{1, 3-5}".parse().unwrap();
+        assert_eq!(ranges, LineRanges::new().range(1..=1).range(3..=5));
+        assert_eq!("".parse::<LineRanges>().unwrap(), LineRanges::new());
+        for invalid in ["{1", "1-", "5-3", "a", "1-2-3"] {
+            assert!(invalid.parse::<LineRanges>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn line_highlight_from_theme() {
+        let syn_set = crate::syntax::extra_newlines();
+        let theme_set = crate::theme::extra();
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        let renderer = Renderer::new().highlight_lines(LineRanges::new().range(1..=1));
+        for name in EmbeddedThemeName::iter() {
+            let theme = &theme_set[name];
+            let html = renderer
+                .render(SAMPLE_RUST, &syn_set, syntax, theme)
+                .unwrap();
+            let Some(line_highlight) = theme.settings.line_highlight else {
+                // The only theme without a line highlight
+                assert_eq!(name, EmbeddedThemeName::Ansi);
+                continue;
+            };
+            let expected = match AnsiColor::from(line_highlight) {
+                AnsiColor::Palette(index) => format!("code-line-highlighted ansi-bg-{index}\""),
+                _ => {
+                    let color = css_color(line_highlight).unwrap();
+                    format!(" style=\"background-color:{color};display:block;\">")
+                }
+            };
+            assert!(html.contains(&expected), "{name}");
+        }
+    }
+
+    #[test]
+    fn palette_colors() {
+        let syn_set = crate::syntax::extra_newlines();
+        let theme_set = crate::theme::extra();
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        let base16 = &theme_set[EmbeddedThemeName::Base16];
+        let code = "// hi\n";
+
+        let html = Renderer::new().render(code, &syn_set, syntax, base16);
+        insta::assert_snapshot!(html.unwrap(), @r#"
+        <pre class="code ansi-fg-7 ansi-bg-0"><code><span class="code-line" data-line="1"><span class="ansi-fg-8">// hi</span>
+        </span></code></pre>
+        "#);
+        let tango = Renderer::new().palette(AnsiPalette::TANGO);
+        let html = tango.render(code, &syn_set, syntax, base16);
+        insta::assert_snapshot!(html.unwrap(), @r#"
+        <pre class="code" style="color:#d3d7cf;background-color:#2e3436;"><code><span class="code-line" data-line="1"><span style="color:#555753;">// hi</span>
+        </span></code></pre>
+        "#);
+    }
+
+    #[test]
+    fn no_theme_background() {
+        let syn_set = crate::syntax::extra_newlines();
+        let mut theme = crate::theme::extra()[EmbeddedThemeName::Nord].clone();
+        theme.settings.background = None;
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        let html = Renderer::new()
+            .render(SAMPLE_RUST, &syn_set, syntax, &theme)
+            .unwrap();
+        // Tokens on syntect's fallback background don't get their own background
+        assert!(!html.contains("background-color"), "{html}");
+    }
+
+    #[test]
+    fn classed() {
+        let html = render(&Renderer::new(), Some(ClassStyle::Spaced));
+        insta::assert_snapshot!(html, @r#"
+        <pre class="code"><code><span class="code-line" data-line="1"><span class="source rust"><span class="comment line double-slash rust"><span class="punctuation definition comment rust">//</span> &lt;hi&gt;</span></span>
+        </span><span class="code-line" data-line="2"><span class="source rust"><span class="meta function rust"><span class="meta function rust"><span class="storage type function rust">fn</span> </span><span class="entity name function rust">main</span></span><span class="meta function rust"><span class="meta function parameters rust"><span class="punctuation section parameters begin rust">(</span></span><span class="meta function rust"><span class="meta function parameters rust"><span class="punctuation section parameters end rust">)</span></span></span></span><span class="meta function rust"> </span><span class="meta function rust"><span class="meta block rust"><span class="punctuation section block begin rust">{</span></span></span></span>
+        </span><span class="code-line" data-line="3"><span class="source rust"><span class="meta function rust"><span class="meta block rust">    <span class="storage type rust">let</span> s <span class="keyword operator assignment rust">=</span> <span class="string quoted double rust"><span class="punctuation definition string begin rust">&quot;</span></span></span></span></span>
+        </span><span class="code-line" data-line="4"><span class="source rust"><span class="meta function rust"><span class="meta block rust"><span class="string quoted double rust"><span class="punctuation definition string end rust">&quot;</span></span><span class="punctuation terminator rust">;</span></span></span></span>
+        </span><span class="code-line" data-line="5"><span class="source rust"><span class="meta function rust"><span class="meta block rust"></span><span class="meta block rust"><span class="punctuation section block end rust">}</span></span></span></span>
+        </span></code></pre>
+        "#);
+    }
+
+    #[test]
+    fn inline() {
+        let html = render(&Renderer::new(), None);
+        insta::assert_snapshot!(html, @r#"
+        <pre class="code" style="color:#d8dee9;background-color:#2e3440;"><code><span class="code-line" data-line="1"><span style="color:#616e88;">// &lt;hi&gt;</span>
+        </span><span class="code-line" data-line="2"><span style="color:#81a1c1;">fn</span><span style="color:#d8dee9;"> </span><span style="color:#88c0d0;">main</span><span style="color:#d8dee9;">() {</span>
+        </span><span class="code-line" data-line="3"><span style="color:#d8dee9;">    </span><span style="color:#81a1c1;">let</span><span style="color:#d8dee9;"> s </span><span style="color:#81a1c1;">=</span><span style="color:#d8dee9;"> </span><span style="color:#a3be8c;">&quot;</span>
+        </span><span class="code-line" data-line="4"><span style="color:#a3be8c;">&quot;</span><span style="color:#eceff4;">;</span>
+        </span><span class="code-line" data-line="5"><span style="color:#d8dee9;">}</span>
+        </span></code></pre>
+        "#);
+    }
+
+    #[test]
+    fn everything() {
+        let renderer = Renderer::new()
+            .line_numbers(true)
+            .first_line(9)
+            .line_ids("ex-")
+            .highlight_lines("{10-11}".parse().unwrap())
+            .caption("src/<main>.rs");
+        let inline = render(&renderer, None);
+        insta::assert_snapshot!(inline, @r#"
+        <figure>
+        <figcaption>src/&lt;main&gt;.rs</figcaption>
+        <pre class="code" style="color:#d8dee9;background-color:#2e3440;"><code><span class="code-line" id="ex-9" data-line="9"><span class="code-line-number" style="color:#d8dee966;background-color:#2e3440;user-select:none;"> 9 </span><span style="color:#616e88;">// &lt;hi&gt;</span>
+        </span><span class="code-line code-line-highlighted" id="ex-10" data-line="10" style="background-color:#434c5e52;display:block;"><span class="code-line-number" style="color:#d8dee966;background-color:#2e3440;user-select:none;">10 </span><span style="color:#81a1c1;">fn</span><span style="color:#d8dee9;"> </span><span style="color:#88c0d0;">main</span><span style="color:#d8dee9;">() {</span>
+        </span><span class="code-line code-line-highlighted" id="ex-11" data-line="11" style="background-color:#434c5e52;display:block;"><span class="code-line-number" style="color:#d8dee966;background-color:#2e3440;user-select:none;">11 </span><span style="color:#d8dee9;">    </span><span style="color:#81a1c1;">let</span><span style="color:#d8dee9;"> s </span><span style="color:#81a1c1;">=</span><span style="color:#d8dee9;"> </span><span style="color:#a3be8c;">&quot;</span>
+        </span><span class="code-line" id="ex-12" data-line="12"><span class="code-line-number" style="color:#d8dee966;background-color:#2e3440;user-select:none;">12 </span><span style="color:#a3be8c;">&quot;</span><span style="color:#eceff4;">;</span>
+        </span><span class="code-line" id="ex-13" data-line="13"><span class="code-line-number" style="color:#d8dee966;background-color:#2e3440;user-select:none;">13 </span><span style="color:#d8dee9;">}</span>
+        </span></code></pre>
+        </figure>
+        "#);
+        let prefixed = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+        let classed = render(&renderer, Some(prefixed));
+        insta::assert_snapshot!(classed, @r#"
+        <figure>
+        <figcaption>src/&lt;main&gt;.rs</figcaption>
+        <pre class="hl-code"><code><span class="hl-code-line" id="ex-9" data-line="9"><span class="hl-code-line-number"> 9 </span><span class="hl-source hl-rust"><span class="hl-comment hl-line hl-double-slash hl-rust"><span class="hl-punctuation hl-definition hl-comment hl-rust">//</span> &lt;hi&gt;</span></span>
+        </span><span class="hl-code-line hl-code-line-highlighted" id="ex-10" data-line="10"><span class="hl-code-line-number">10 </span><span class="hl-source hl-rust"><span class="hl-meta hl-function hl-rust"><span class="hl-meta hl-function hl-rust"><span class="hl-storage hl-type hl-function hl-rust">fn</span> </span><span class="hl-entity hl-name hl-function hl-rust">main</span></span><span class="hl-meta hl-function hl-rust"><span class="hl-meta hl-function hl-parameters hl-rust"><span class="hl-punctuation hl-section hl-parameters hl-begin hl-rust">(</span></span><span class="hl-meta hl-function hl-rust"><span class="hl-meta hl-function hl-parameters hl-rust"><span class="hl-punctuation hl-section hl-parameters hl-end hl-rust">)</span></span></span></span><span class="hl-meta hl-function hl-rust"> </span><span class="hl-meta hl-function hl-rust"><span class="hl-meta hl-block hl-rust"><span class="hl-punctuation hl-section hl-block hl-begin hl-rust">{</span></span></span></span>
+        </span><span class="hl-code-line hl-code-line-highlighted" id="ex-11" data-line="11"><span class="hl-code-line-number">11 </span><span class="hl-source hl-rust"><span class="hl-meta hl-function hl-rust"><span class="hl-meta hl-block hl-rust">    <span class="hl-storage hl-type hl-rust">let</span> s <span class="hl-keyword hl-operator hl-assignment hl-rust">=</span> <span class="hl-string hl-quoted hl-double hl-rust"><span class="hl-punctuation hl-definition hl-string hl-begin hl-rust">&quot;</span></span></span></span></span>
+        </span><span class="hl-code-line" id="ex-12" data-line="12"><span class="hl-code-line-number">12 </span><span class="hl-source hl-rust"><span class="hl-meta hl-function hl-rust"><span class="hl-meta hl-block hl-rust"><span class="hl-string hl-quoted hl-double hl-rust"><span class="hl-punctuation hl-definition hl-string hl-end hl-rust">&quot;</span></span><span class="hl-punctuation hl-terminator hl-rust">;</span></span></span></span>
+        </span><span class="hl-code-line" id="ex-13" data-line="13"><span class="hl-code-line-number">13 </span><span class="hl-source hl-rust"><span class="hl-meta hl-function hl-rust"><span class="hl-meta hl-block hl-rust"></span><span class="hl-meta hl-block hl-rust"><span class="hl-punctuation hl-section hl-block hl-end hl-rust">}</span></span></span></span>
+        </span></code></pre>
+        </figure>
+        "#);
+    }
+
+    #[test]
+    fn unusual_prefix() {
+        // Classes stay as is in the HTML and only get escaped within the CSS selectors
+        let style = ClassStyle::SpacedPrefixed { prefix: "c++" };
+        let html = render(&Renderer::new(), Some(style));
+        assert!(html.starts_with("<pre class=\"c++code\"><code><span class=\"c++code-line\""));
+        assert!(html.contains("<span class=\"c++source c++rust\">"));
+        let theme_set = crate::theme::extra();
+        let css = crate::theme::css_for_theme(&theme_set[EmbeddedThemeName::Nord], style);
+        assert!(css.contains(".c\\2b \\2b code .c\\2b \\2b code-line-number {"));
+    }
+}
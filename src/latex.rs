@@ -0,0 +1,436 @@
+//! LaTeX output for highlighted code
+//!
+//! The [`Renderer`] emits a [`fancyvrb`](https://ctan.org/pkg/fancyvrb) `Verbatim` environment
+//! where each token is wrapped in `\textcolor`, `\textbf`, `\textit`, and `\underline` commands.
+//! The colors and helper macros it references are defined by the preamble from [`preamble()`] (or
+//! [`Renderer::preamble()`] for arbitrary themes), which has to be included before
+//! `\begin{document}`
+//!
+//! Colors are named after their hex value (e.g. `TF81a1c1`), so the preambles for several themes
+//! can be combined in the same document. The theme's overall colors are also available as
+//! `TFforeground` and `TFbackground` for things like framing the code blocks
+//!
+//! # Example
+//!
+//! ```
+//! use two_face::{latex::{preamble, Renderer}, theme::{extra, EmbeddedThemeName}};
+//!
+//! let syn_set = two_face::syntax::extra_newlines();
+//! let theme_set = extra();
+//!
+//! let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+//! let body = Renderer::new()
+//!     .line_numbers(true)
+//!     .render("fn main() {}\n", &syn_set, syntax, &theme_set[EmbeddedThemeName::Nord])
+//!     .unwrap();
+//! assert!(body.starts_with("\\begin{Verbatim}[commandchars=\\\\\\{\\}"));
+//!
+//! let preamble = preamble(EmbeddedThemeName::Nord);
+//! assert!(preamble.contains("\\definecolor{TF81a1c1}{HTML}{81A1C1}\n"));
+//! ```
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, FontStyle, Theme},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+    Error,
+};
+
+use crate::{
+    color,
+    html::AnsiPalette,
+    runs::{merge_ranges, TAB_WIDTH},
+    theme::{extra, EmbeddedThemeName},
+};
+
+const MACROS: &str = r"\def\TFbs{\char`\\}
+\def\TFob{\char`\{}
+\def\TFcb{\char`\}}
+\def\TFbg#1#2{{\setlength{\fboxsep}{0pt}\colorbox{#1}{\strut #2}}}
+";
+
+/// Generates the preamble for one of the embedded themes using a default [`Renderer`]
+///
+/// # Example
+///
+/// ```
+/// use two_face::{latex::preamble, theme::EmbeddedThemeName};
+///
+/// let preamble = preamble(EmbeddedThemeName::Nord);
+/// assert!(preamble.starts_with("% Nord\n\\usepackage{fancyvrb}\n"));
+/// assert!(preamble.contains("\\definecolor{TFbackground}{HTML}{2E3440}\n"));
+/// ```
+pub fn preamble(name: EmbeddedThemeName) -> String {
+    Renderer::new().preamble(extra().get(name))
+}
+
+/// Renders highlighted code into a LaTeX `Verbatim` environment
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    line_numbers: bool,
+    first_line: usize,
+    palette: AnsiPalette,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            line_numbers: false,
+            first_line: 1,
+            palette: AnsiPalette::default(),
+        }
+    }
+}
+
+impl Renderer {
+    /// Creates a renderer with the default settings
+    ///
+    /// By default there are no line numbers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to have `fancyvrb` display line numbers on the left
+    pub fn line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
+        self
+    }
+
+    /// The number used for the first line (defaults to `1`)
+    pub fn first_line(mut self, number: usize) -> Self {
+        self.first_line = number;
+        self
+    }
+
+    /// The palette used to resolve palette colors (defaults to [`AnsiPalette::XTERM`])
+    ///
+    /// The terminal's default colors have no equivalent, so they're left to the surrounding
+    /// document. Make sure to generate the preamble with the same palette
+    pub fn palette(mut self, palette: AnsiPalette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Generates a preamble defining the packages, macros, and colors used when rendering with
+    /// `theme`
+    pub fn preamble(&self, theme: &Theme) -> String {
+        let settings = &theme.settings;
+        let mut out = format!("% {}\n", theme.name.as_deref().unwrap_or("Unnamed theme"));
+        out.push_str("\\usepackage{fancyvrb}\n\\usepackage{xcolor}\n");
+        out.push_str(MACROS);
+
+        let background = settings.background;
+        let named = [
+            ("foreground", settings.foreground),
+            ("background", background),
+        ];
+        for (name, color) in named {
+            if let Some(color) = color.and_then(|color| self.palette.resolve(color, background)) {
+                write_definition(&mut out, name, color);
+            }
+        }
+
+        let scope_colors = theme
+            .scopes
+            .iter()
+            .flat_map(|item| [item.style.foreground, item.style.background]);
+        let colors: BTreeSet<_> = named
+            .into_iter()
+            .map(|(_, color)| color)
+            .chain(scope_colors)
+            .flatten()
+            .filter_map(|color| self.palette.resolve(color, background))
+            .map(|color| (color.r, color.g, color.b))
+            .collect();
+        for (r, g, b) in colors {
+            write_definition(&mut out, &hex(color::rgb(r, g, b)), color::rgb(r, g, b));
+        }
+
+        out
+    }
+
+    /// Renders `s` using the colors from `theme`
+    ///
+    /// All of the colors referenced in the output are defined by [`Renderer::preamble()`]
+    pub fn render(
+        &self,
+        s: &str,
+        syntax_set: &SyntaxSet,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+    ) -> Result<String, Error> {
+        let settings = &theme.settings;
+        let background = settings.background;
+        let foreground = settings
+            .foreground
+            .and_then(|color| self.palette.resolve(color, background));
+
+        let mut out = String::from("\\begin{Verbatim}[commandchars=\\\\\\{\\}");
+        if let Some(foreground) = foreground {
+            write!(out, ",formatcom=\\color{{TF{}}}", hex(foreground)).expect("Infallible");
+        }
+        if self.line_numbers {
+            out.push_str(",numbers=left");
+            if self.first_line != 1 {
+                write!(out, ",firstnumber={}", self.first_line).expect("Infallible");
+            }
+        }
+        out.push_str("]\n");
+
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        for line in LinesWithEndings::from(s) {
+            let ranges = highlighter.highlight_line(line, syntax_set)?;
+            for (style, text) in merge_ranges(ranges, Some(TAB_WIDTH)) {
+                let mut closing = 0;
+                if Some(style.background) != background {
+                    if let Some(color) = self.palette.resolve(style.background, background) {
+                        write!(out, "\\TFbg{{TF{}}}{{", hex(color)).expect("Infallible");
+                        closing += 1;
+                    }
+                }
+                match self.palette.resolve(style.foreground, background) {
+                    Some(color) if Some(color) != foreground => {
+                        write!(out, "\\textcolor{{TF{}}}{{", hex(color)).expect("Infallible");
+                        closing += 1;
+                    }
+                    _ => {}
+                }
+                for (flag, command) in [
+                    (FontStyle::BOLD, "\\textbf{"),
+                    (FontStyle::ITALIC, "\\textit{"),
+                    (FontStyle::UNDERLINE, "\\underline{"),
+                ] {
+                    if style.font_style.contains(flag) {
+                        out.push_str(command);
+                        closing += 1;
+                    }
+                }
+                push_escaped(&mut out, &text);
+                out.extend(std::iter::repeat('}').take(closing));
+            }
+            out.push('\n');
+        }
+        out.push_str("\\end{Verbatim}\n");
+
+        Ok(out)
+    }
+}
+
+fn hex(Color { r, g, b, .. }: Color) -> String {
+    format!("{r:02x}{g:02x}{b:02x}")
+}
+
+fn write_definition(out: &mut String, name: &str, Color { r, g, b, .. }: Color) {
+    writeln!(
+        out,
+        "\\definecolor{{TF{name}}}{{HTML}}{{{r:02X}{g:02X}{b:02X}}}"
+    )
+    .expect("Infallible");
+}
+
+/// Escapes `s` for a `Verbatim` environment using `commandchars=\\\{\}`
+///
+/// Only the command characters are special there. Control characters are written in caret
+/// notation since TeX rejects most of them outright
+fn push_escaped(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\TFbs{}"),
+            '{' => out.push_str("\\TFob{}"),
+            '}' => out.push_str("\\TFcb{}"),
+            '\0'..='\x1f' | '\x7f' => {
+                out.push_str("^^");
+                out.push(char::from(c as u8 ^ 0x40));
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(renderer: &Renderer, code: &str, ext: &str, theme: EmbeddedThemeName) -> String {
+        let syn_set = crate::syntax::extra_newlines();
+        let theme_set = extra();
+        let syntax = syn_set.find_syntax_by_extension(ext).unwrap();
+        renderer
+            .render(code, &syn_set, syntax, &theme_set[theme])
+            .unwrap()
+    }
+
+    /// Reverses the escaping while checking that all of the groups are balanced
+    fn unescape(body: &str) -> String {
+        let body = body
+            .strip_prefix("\\begin{Verbatim}[")
+            .and_then(|body| body.split_once("]\n"))
+            .map(|(_, body)| body)
+            .and_then(|body| body.strip_suffix("\\end{Verbatim}\n"))
+            .expect("Wrapped in a Verbatim environment");
+
+        let mut text = String::new();
+        let mut depth = 0_usize;
+        let mut rest = body;
+        while let Some(c) = rest.chars().next() {
+            if let Some(after) = rest.strip_prefix('\\') {
+                let (command, after) = after
+                    .split_once('{')
+                    .expect("Commands always take an argument");
+                rest = after;
+                match command {
+                    "TFbs" | "TFob" | "TFcb" => {
+                        rest = rest.strip_prefix('}').expect("Empty argument");
+                        text.push(match command {
+                            "TFbs" => '\\',
+                            "TFob" => '{',
+                            _ => '}',
+                        });
+                    }
+                    "textcolor" | "TFbg" => {
+                        let (color, after) = rest.split_once("}{").expect("Color then text");
+                        assert!(color.starts_with("TF") && !color.contains(['{', '}', '\\']));
+                        rest = after;
+                        depth += 1;
+                    }
+                    "textbf" | "textit" | "underline" => depth += 1,
+                    _ => panic!("Unexpected command: {command}"),
+                }
+                continue;
+            }
+            match c {
+                '{' => panic!("Stray group in: {body}"),
+                '}' => depth = depth.checked_sub(1).expect("Balanced groups"),
+                '\n' => assert_eq!(depth, 0, "Groups span lines in: {body}"),
+                _ => {}
+            }
+            if c != '}' {
+                text.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        assert_eq!(depth, 0);
+        text
+    }
+
+    #[test]
+    fn escaping_for_every_syntax() {
+        let code = "\\begin{x} $a_1^2$ & #b % ~c 'd' \"e\" /* {f} */ // g\n\
+            \\\\} {{ \\n <h> `i` @j \x1b[0m\n\
+            # \\{\\}\n";
+        let expected = code.replace('\x1b', "^^[");
+        let syn_set = crate::syntax::extra_newlines();
+        let theme_set = extra();
+        for theme in [EmbeddedThemeName::Nord, EmbeddedThemeName::Base16] {
+            let theme = &theme_set[theme];
+            for syntax in syn_set.syntaxes() {
+                let body = Renderer::new()
+                    .render(code, &syn_set, syntax, theme)
+                    .unwrap();
+                assert_eq!(unescape(&body), expected, "Syntax: {}", syntax.name);
+            }
+        }
+    }
+
+    #[test]
+    fn rust() {
+        let renderer = Renderer::new().line_numbers(true).first_line(9);
+        let code = "fn main() {\n\tprintln!(\"{}\\n\", 1);\n}\n";
+        let latex = render(&renderer, code, "rs", EmbeddedThemeName::Nord);
+        insta::assert_snapshot!(latex, @r#"
+        \begin{Verbatim}[commandchars=\\\{\},formatcom=\color{TFd8dee9},numbers=left,firstnumber=9]
+        \textcolor{TF81a1c1}{fn} \textcolor{TF88c0d0}{main}() \TFob{}
+            println!(\textcolor{TFa3be8c}{"}\textcolor{TFebcb8b}{\TFob{}\TFcb{}\TFbs{}n}\textcolor{TFa3be8c}{"}\textcolor{TFeceff4}{,} \textcolor{TFb48ead}{1})\textcolor{TFeceff4}{;}
+        \TFcb{}
+        \end{Verbatim}
+        "#);
+    }
+
+    #[test]
+    fn font_styles() {
+        let code = "# Title\n\n*some* **text**\n";
+        let latex = render(
+            &Renderer::new(),
+            code,
+            "md",
+            EmbeddedThemeName::InspiredGithub,
+        );
+        insta::assert_snapshot!(latex, @r"
+        \begin{Verbatim}[commandchars=\\\{\},formatcom=\color{TF323232}]
+        \textcolor{TFa71d5d}{\textbf{#}}\textbf{ Title}
+
+        \textcolor{TFa71d5d}{\textit{*}}\textit{some}\textcolor{TFa71d5d}{\textit{*}} \textcolor{TFa71d5d}{\textbf{**}}\textbf{text}\textcolor{TFa71d5d}{\textbf{**}}
+        \end{Verbatim}
+        ");
+    }
+
+    #[test]
+    fn palette_theme() {
+        let code = "echo $HOME\n";
+        let latex = render(&Renderer::new(), code, "sh", EmbeddedThemeName::Ansi);
+        insta::assert_snapshot!(latex, @r"
+        \begin{Verbatim}[commandchars=\\\{\}]
+        \textcolor{TF00cdcd}{echo} $HOME
+        \end{Verbatim}
+        ");
+        let renderer = Renderer::new().palette(AnsiPalette::TANGO);
+        let latex = render(&renderer, code, "sh", EmbeddedThemeName::Base16);
+        insta::assert_snapshot!(latex, @r"
+        \begin{Verbatim}[commandchars=\\\{\},formatcom=\color{TFd3d7cf}]
+        \textcolor{TF06989a}{echo} $HOME
+        \end{Verbatim}
+        ");
+    }
+
+    #[test]
+    fn preambles() {
+        let syn_set = crate::syntax::extra_newlines();
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        let theme_set = extra();
+        let code = "fn main() { let s = \"\\n\"; } // hi\n";
+        for &name in crate::theme::EmbeddedLazyThemeSet::theme_names() {
+            let preamble = preamble(name);
+            let body = Renderer::new()
+                .render(code, &syn_set, syntax, &theme_set[name])
+                .unwrap();
+            // Every color referenced by the output needs to be defined
+            for (start, _) in body.match_indices("{TF") {
+                let color = &body[start + 1..];
+                let color = &color[..color.find('}').unwrap()];
+                assert!(
+                    preamble.contains(&format!("\\definecolor{{{color}}}")),
+                    "{name:?} is missing {color}"
+                );
+            }
+        }
+
+        insta::assert_snapshot!(preamble(EmbeddedThemeName::Nord), @r"
+        % Nord
+        \usepackage{fancyvrb}
+        \usepackage{xcolor}
+        \def\TFbs{\char`\\}
+        \def\TFob{\char`\{}
+        \def\TFcb{\char`\}}
+        \def\TFbg#1#2{{\setlength{\fboxsep}{0pt}\colorbox{#1}{\strut #2}}}
+        \definecolor{TFforeground}{HTML}{D8DEE9}
+        \definecolor{TFbackground}{HTML}{2E3440}
+        \definecolor{TF2e3440}{HTML}{2E3440}
+        \definecolor{TF5e81ac}{HTML}{5E81AC}
+        \definecolor{TF616e88}{HTML}{616E88}
+        \definecolor{TF727884}{HTML}{727884}
+        \definecolor{TF81a1c1}{HTML}{81A1C1}
+        \definecolor{TF88c0d0}{HTML}{88C0D0}
+        \definecolor{TF8fbcbb}{HTML}{8FBCBB}
+        \definecolor{TFa3be8c}{HTML}{A3BE8C}
+        \definecolor{TFb48ead}{HTML}{B48EAD}
+        \definecolor{TFbf616a}{HTML}{BF616A}
+        \definecolor{TFd08770}{HTML}{D08770}
+        \definecolor{TFd8dee9}{HTML}{D8DEE9}
+        \definecolor{TFebcb8b}{HTML}{EBCB8B}
+        \definecolor{TFeceff4}{HTML}{ECEFF4}
+        ");
+    }
+}
@@ -66,6 +66,10 @@
 //! | :---: | :--- |
 //! | `syntect-onig` / `syntect-fancy` | Enables the minimal feature set that we require from `syntect` |
 //! | `syntect-default-onig` / `syntect-default-fancy` | The mimimal feature sets along with `syntect`'s default feature set (useful when using the `syntect` re-export) |
+//! | `detect-background` | Enables the `background` module for detecting whether a terminal is light or dark |
+//! | `plist-load` | Enables lazily loading `.tmTheme` files into a `LazyThemeSet` (included in the `syntect-default-*` features) |
+//! | `watch` | Enables the `theme::watch` module for hot-reloading a folder of `.tmTheme` files |
+//! | `import` | Enables the `theme::import` module for importing VS Code and Sublime Text themes |
 //!
 //! ## Embedded Asset Sizes
 //!
@@ -161,7 +165,17 @@
 pub struct ReadmeDoctests;
 
 pub mod acknowledgement;
+#[cfg(feature = "detect-background")]
+pub mod background;
+mod color;
+pub mod document;
+pub mod html;
+pub mod latex;
+pub mod rtf;
+mod runs;
+pub mod svg;
 pub mod syntax;
+pub mod terminal;
 pub mod theme;
 
 /// Dependency re-exports for user's convenience
@@ -0,0 +1,344 @@
+//! RTF output for pasting highlighted code into word processors, slide tools, and email clients
+//!
+//! The [`Renderer`] produces a complete RTF document with a font table for the monospace font
+//! and a color table built from all of the colors used by the [`Theme`]. Each line becomes its own
+//! paragraph and all non-ASCII text is written out as `\uN` escapes, so the output is plain ASCII
+//!
+//! # Example
+//!
+//! ```
+//! use two_face::{rtf::Renderer, theme::{extra, EmbeddedThemeName}};
+//!
+//! let syn_set = two_face::syntax::extra_newlines();
+//! let theme_set = extra();
+//!
+//! let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+//! let rtf = Renderer::new()
+//!     .font("Fira Code")
+//!     .font_size(12)
+//!     .render("fn main() {}\n", &syn_set, syntax, &theme_set[EmbeddedThemeName::Nord])
+//!     .unwrap();
+//! assert!(rtf.starts_with("{\\rtf1\\ansi"));
+//! assert!(rtf.contains("{\\fonttbl{\\f0\\fmodern\\fcharset0 Fira Code;}}"));
+//! ```
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, FontStyle, Theme},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+    Error,
+};
+
+use crate::{html::AnsiPalette, runs::merge_ranges};
+
+/// Renders highlighted code into an RTF document
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    font: String,
+    font_size: u32,
+    palette: AnsiPalette,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            font: String::from("Courier New"),
+            font_size: 10,
+            palette: AnsiPalette::default(),
+        }
+    }
+}
+
+impl Renderer {
+    /// Creates a renderer with the default settings
+    ///
+    /// By default the code is set in 10pt Courier New
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The name of the font used for the code
+    pub fn font(mut self, font: impl Into<String>) -> Self {
+        self.font = font.into();
+        self
+    }
+
+    /// The font size in points
+    pub fn font_size(mut self, points: u32) -> Self {
+        self.font_size = points;
+        self
+    }
+
+    /// The palette used to resolve palette colors (defaults to [`AnsiPalette::XTERM`])
+    ///
+    /// The terminal's default colors use the document's automatic color instead
+    pub fn palette(mut self, palette: AnsiPalette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Renders `s` using the colors from `theme`
+    pub fn render(
+        &self,
+        s: &str,
+        syntax_set: &SyntaxSet,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+    ) -> Result<String, Error> {
+        let colors = ColorTable::new(theme, &self.palette);
+        let background = theme.settings.background;
+
+        let mut out = String::from("{\\rtf1\\ansi\\ansicpg1252\\deff0\\uc1\n");
+        out.push_str("{\\fonttbl{\\f0\\fmodern\\fcharset0 ");
+        push_escaped(&mut out, &self.font);
+        out.push_str(";}}\n");
+        colors.write(&mut out);
+        write!(out, "\\f0\\fs{}", self.font_size * 2).expect("Infallible");
+        let foreground = theme.settings.foreground;
+        if let Some(index) = foreground.and_then(|color| colors.index(color)) {
+            write!(out, "\\cf{index}").expect("Infallible");
+        }
+        if let Some(index) = background.and_then(|color| colors.index(color)) {
+            write!(out, "\\cb{index}\\chcbpat{index}").expect("Infallible");
+        }
+        out.push('\n');
+
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        for line in LinesWithEndings::from(s) {
+            let ranges = highlighter.highlight_line(line, syntax_set)?;
+            for (style, text) in merge_ranges(ranges, None) {
+                let mut controls = String::new();
+                if Some(style.foreground) != foreground {
+                    let index = colors.index(style.foreground).unwrap_or(0);
+                    write!(controls, "\\cf{index}").expect("Infallible");
+                }
+                if Some(style.background) != background {
+                    let index = colors.index(style.background).unwrap_or(0);
+                    write!(controls, "\\cb{index}\\chcbpat{index}").expect("Infallible");
+                }
+                for (flag, control) in [
+                    (FontStyle::BOLD, "\\b"),
+                    (FontStyle::ITALIC, "\\i"),
+                    (FontStyle::UNDERLINE, "\\ul"),
+                ] {
+                    if style.font_style.contains(flag) {
+                        controls.push_str(control);
+                    }
+                }
+
+                if controls.is_empty() {
+                    push_escaped(&mut out, &text);
+                } else {
+                    write!(out, "{{{controls} ").expect("Infallible");
+                    push_escaped(&mut out, &text);
+                    out.push('}');
+                }
+            }
+            out.push_str("\\par\n");
+        }
+        out.push_str("}\n");
+
+        Ok(out)
+    }
+}
+
+/// The document's color table where index `0` is reserved for the automatic color
+struct ColorTable<'a> {
+    colors: Vec<Color>,
+    palette: &'a AnsiPalette,
+    background: Option<Color>,
+}
+
+impl<'a> ColorTable<'a> {
+    fn new(theme: &Theme, palette: &'a AnsiPalette) -> Self {
+        let settings = &theme.settings;
+        let background = settings.background;
+        let scope_colors = theme
+            .scopes
+            .iter()
+            .flat_map(|item| [item.style.foreground, item.style.background]);
+        let colors: BTreeSet<_> = [settings.foreground, background]
+            .into_iter()
+            .chain(scope_colors)
+            .flatten()
+            .filter_map(|color| palette.resolve(color, background))
+            .map(|Color { r, g, b, a }| (r, g, b, a))
+            .collect();
+        let colors = colors
+            .into_iter()
+            .map(|(r, g, b, a)| Color { r, g, b, a })
+            .collect();
+
+        Self {
+            colors,
+            palette,
+            background,
+        }
+    }
+
+    /// The index for `color` or `None` for the terminal's default colors
+    fn index(&self, color: Color) -> Option<usize> {
+        let color = self.palette.resolve(color, self.background)?;
+        let position = self.colors.iter().position(|&entry| entry == color);
+        Some(position.map_or(0, |position| position + 1))
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push_str("{\\colortbl;");
+        for Color { r, g, b, .. } in &self.colors {
+            write!(out, "\\red{r}\\green{g}\\blue{b};").expect("Infallible");
+        }
+        out.push_str("}\n");
+    }
+}
+
+/// Escapes `s` for RTF
+///
+/// Anything outside of printable ASCII gets written as UTF-16 code units with `?` as the fallback
+/// for readers that don't understand `\u`
+fn push_escaped(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\t' => out.push_str("\\tab "),
+            ' '..='~' => out.push(c),
+            _ => {
+                let c = if c.is_control() {
+                    char::REPLACEMENT_CHARACTER
+                } else {
+                    c
+                };
+                let mut units = [0; 2];
+                for &unit in c.encode_utf16(&mut units).iter() {
+                    // RTF control words take signed 16-bit parameters
+                    write!(out, "\\u{}?", unit as i16).expect("Infallible");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::theme::{extra, EmbeddedLazyThemeSet, EmbeddedThemeName};
+
+    fn render(renderer: &Renderer, code: &str, ext: &str, theme: &Theme) -> String {
+        let syn_set = crate::syntax::extra_newlines();
+        let syntax = syn_set.find_syntax_by_extension(ext).unwrap();
+        renderer.render(code, &syn_set, syntax, theme).unwrap()
+    }
+
+    #[test]
+    fn rust() {
+        let code = "fn main() {\n\tlet s = \"{\\\\}\";\n}\n";
+        let rtf = render(
+            &Renderer::new(),
+            code,
+            "rs",
+            &extra()[EmbeddedThemeName::Nord],
+        );
+        insta::assert_snapshot!(rtf, @r#"
+        {\rtf1\ansi\ansicpg1252\deff0\uc1
+        {\fonttbl{\f0\fmodern\fcharset0 Courier New;}}
+        {\colortbl;\red46\green52\blue64;\red94\green129\blue172;\red97\green110\blue136;\red114\green120\blue132;\red129\green161\blue193;\red136\green192\blue208;\red143\green188\blue187;\red163\green190\blue140;\red180\green142\blue173;\red191\green97\blue106;\red208\green135\blue112;\red216\green222\blue233;\red235\green203\blue139;\red236\green239\blue244;}
+        \f0\fs20\cf12\cb1\chcbpat1
+        {\cf5 fn} {\cf6 main}() \{\par
+        \tab {\cf5 let} s {\cf5 =} {\cf8 "\{}{\cf13 \\\\}{\cf8 \}"}{\cf14 ;}\par
+        \}\par
+        }
+        "#);
+    }
+
+    #[test]
+    fn font_styles() {
+        let code = "# Title\n\n*some* **text**\n";
+        let renderer = Renderer::new().font("Menlo {Bold}").font_size(9);
+        let rtf = render(
+            &renderer,
+            code,
+            "md",
+            &extra()[EmbeddedThemeName::InspiredGithub],
+        );
+        insta::assert_snapshot!(rtf, @r"
+        {\rtf1\ansi\ansicpg1252\deff0\uc1
+        {\fonttbl{\f0\fmodern\fcharset0 Menlo \{Bold\};}}
+        {\colortbl;\red0\green134\blue179;\red24\green54\blue145;\red50\green50\blue50;\red65\green131\blue196;\red85\green165\blue50;\red98\green163\blue92;\red99\green163\blue92;\red121\green93\blue163;\red150\green152\blue150;\red167\green29\blue93;\red179\green179\blue179;\red181\green42\blue29;\red189\green44\blue0;\red234\green255\blue234;\red237\green106\blue67;\red245\green245\blue245;\red255\green236\blue236;\red255\green255\blue255;}
+        \f0\fs18\cf3\cb18\chcbpat18
+        {\cf10\b #}{\b  Title}\par
+        \par
+        {\cf10\i *}{\i some}{\cf10\i *} {\cf10\b **}{\b text}{\cf10\b **}\par
+        }
+        ");
+    }
+
+    #[test]
+    fn palette_theme() {
+        let code = "echo $HOME\n";
+        let rtf = render(
+            &Renderer::new(),
+            code,
+            "sh",
+            &extra()[EmbeddedThemeName::Ansi],
+        );
+        insta::assert_snapshot!(rtf, @r"
+        {\rtf1\ansi\ansicpg1252\deff0\uc1
+        {\fonttbl{\f0\fmodern\fcharset0 Courier New;}}
+        {\colortbl;\red0\green0\blue238;\red0\green205\blue0;\red0\green205\blue205;\red205\green0\blue0;\red205\green0\blue205;\red205\green205\blue0;}
+        \f0\fs20
+        {\cf3 echo} $HOME\par
+        }
+        ");
+    }
+
+    #[test]
+    fn unicode() {
+        let mut out = String::new();
+        push_escaped(&mut out, "caf\u{e9} \u{2603} \u{1f980}\x1b");
+        insta::assert_snapshot!(out, @r"caf\u233? \u9731? \u-10178?\u-8832?\u-3?");
+    }
+
+    #[test]
+    fn every_embedded_theme() {
+        let theme_set = extra();
+        let code = "fn main() {\n    // \u{1f980}\n    let s = \"\\n\";\n}\n";
+        for &name in EmbeddedLazyThemeSet::theme_names() {
+            let theme = &theme_set[name];
+            let rtf = render(&Renderer::new(), code, "rs", theme);
+            assert!(rtf.is_ascii());
+
+            let entries = ColorTable::new(theme, &AnsiPalette::default()).colors.len();
+            let mut depth = 0_usize;
+            let mut chars = rtf.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' if chars.peek().is_some_and(|c| "\\{}".contains(*c)) => {
+                        chars.next();
+                    }
+                    '{' => depth += 1,
+                    '}' => depth = depth.checked_sub(1).expect("Balanced groups"),
+                    _ => {}
+                }
+            }
+            assert_eq!(depth, 0, "{name:?}");
+
+            for control in ["\\cf", "\\cb"] {
+                for (start, _) in rtf.match_indices(control) {
+                    let digits: String = rtf[start + control.len()..]
+                        .chars()
+                        .take_while(char::is_ascii_digit)
+                        .collect();
+                    let index: usize = digits.parse().unwrap();
+                    assert!(index <= entries, "{name:?} uses missing color {index}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Internal helpers for turning highlighted lines into runs of text for the renderers
+
+/// The tab width used by renderers that need columns to line up
+pub(crate) const TAB_WIDTH: usize = 4;
+
+/// Merges adjacent ranges that share a key (e.g. a [`syntect::highlighting::Style`])
+///
+/// Line endings and empty ranges are dropped, and tabs are expanded to spaces when `tab_width` is
+/// set
+pub(crate) fn merge_ranges<K, S>(
+    ranges: impl IntoIterator<Item = (K, S)>,
+    tab_width: Option<usize>,
+) -> Vec<(K, String)>
+where
+    K: PartialEq,
+    S: AsRef<str>,
+{
+    let mut runs: Vec<(K, String)> = Vec::new();
+    let mut column = 0;
+    for (key, text) in ranges {
+        let text = text.as_ref().trim_end_matches(['\r', '\n']);
+        let mut expanded = String::with_capacity(text.len());
+        for c in text.chars() {
+            match tab_width {
+                Some(tab_width) if c == '\t' => {
+                    let spaces = tab_width - column % tab_width;
+                    expanded.extend(std::iter::repeat(' ').take(spaces));
+                    column += spaces;
+                }
+                _ => {
+                    expanded.push(c);
+                    column += 1;
+                }
+            }
+        }
+
+        match runs.last_mut() {
+            _ if expanded.is_empty() => {}
+            Some((last_key, last_text)) if *last_key == key => last_text.push_str(&expanded),
+            _ => runs.push((key, expanded)),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_and_expand() {
+        let ranges = [(1, "a\t"), (1, "b"), (2, ""), (2, "\tc\r\n")];
+        assert_eq!(
+            merge_ranges(ranges, Some(TAB_WIDTH)),
+            [(1, "a   b".to_owned()), (2, "   c".to_owned())]
+        );
+        assert_eq!(
+            merge_ranges(ranges, None),
+            [(1, "a\tb".to_owned()), (2, "\tc".to_owned())]
+        );
+    }
+}
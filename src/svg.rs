@@ -0,0 +1,406 @@
+//! Self-contained SVG output for highlighted code
+//!
+//! The output is plain SVG text with no embedded fonts or raster images, so it works for things
+//! like READMEs, slides, and generated docs. All of the layout assumes a monospace font where
+//! every character takes up a single column
+//!
+//! # Example
+//!
+//! ```
+//! use two_face::{svg::Renderer, theme::{extra, EmbeddedThemeName}};
+//!
+//! let syn_set = two_face::syntax::extra_newlines();
+//! let theme_set = extra();
+//!
+//! let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+//! let svg = Renderer::new()
+//!     .window_chrome(true)
+//!     .title("main.rs")
+//!     .line_numbers(true)
+//!     .render("fn main() {}\n", &syn_set, syntax, &theme_set[EmbeddedThemeName::Dracula])
+//!     .unwrap();
+//! assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+//! ```
+
+use std::fmt::Write;
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, FontStyle, Theme},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+    Error,
+};
+
+use crate::{
+    html::AnsiPalette,
+    runs::{merge_ranges, TAB_WIDTH},
+    terminal::AnsiColor,
+};
+
+const FONT_FAMILY: &str =
+    "ui-monospace, SFMono-Regular, Menlo, Consolas, 'Liberation Mono', monospace";
+// All lengths are in tenths of a pixel to keep the output free of float noise
+const FONT_SIZE: u32 = 140;
+const CHAR_WIDTH: u32 = 84;
+const LINE_HEIGHT: u32 = 210;
+const BASELINE: u32 = 150;
+const PADDING: u32 = 160;
+const CHROME_HEIGHT: u32 = 360;
+
+/// Renders highlighted code into an SVG image
+#[derive(Clone, Debug, Default)]
+pub struct Renderer {
+    line_numbers: bool,
+    window_chrome: bool,
+    title: Option<String>,
+    palette: AnsiPalette,
+}
+
+impl Renderer {
+    /// Creates a renderer with the default settings
+    ///
+    /// By default there are no line numbers or window chrome
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to display line numbers
+    pub fn line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
+        self
+    }
+
+    /// Whether to draw a title bar with window buttons above the code
+    pub fn window_chrome(mut self, enabled: bool) -> Self {
+        self.window_chrome = enabled;
+        self
+    }
+
+    /// A title for the image which is displayed in the window chrome when it's enabled
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// The palette used to resolve palette colors (defaults to [`AnsiPalette::XTERM`])
+    ///
+    /// The terminal's default colors have no equivalent, so they leave the background transparent
+    /// and use `currentColor` for text
+    pub fn palette(mut self, palette: AnsiPalette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Renders `s` using the colors from `theme`
+    pub fn render(
+        &self,
+        s: &str,
+        syntax_set: &SyntaxSet,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+    ) -> Result<String, Error> {
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(s) {
+            let ranges = highlighter.highlight_line(line, syntax_set)?;
+            // Expand tabs up front so that the columns line up
+            let tokens = merge_ranges(ranges, Some(TAB_WIDTH));
+            let columns = tokens.iter().map(|(_, text)| text.chars().count()).sum();
+            lines.push((tokens, columns));
+        }
+
+        let number_width = if self.line_numbers {
+            lines.len().max(1).to_string().len() as u32
+        } else {
+            0
+        };
+        let gutter = if self.line_numbers {
+            (number_width + 2) * CHAR_WIDTH
+        } else {
+            0
+        };
+        let max_columns = lines.iter().map(|&(_, columns)| columns).max().unwrap_or(0) as u32;
+        let top = PADDING + if self.window_chrome { CHROME_HEIGHT } else { 0 };
+        let width = (2 * PADDING + gutter + max_columns * CHAR_WIDTH).max(if self.window_chrome {
+            1_000
+        } else {
+            0
+        });
+        let height = top + lines.len() as u32 * LINE_HEIGHT + PADDING;
+
+        let settings = &theme.settings;
+        let foreground = settings.foreground.unwrap_or(AnsiColor::Default.into());
+        // Matches the background that syntect falls back to
+        let background = settings.background.unwrap_or(Color::WHITE);
+        let mut out = String::new();
+        let (width, height) = (px(width), px(height));
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+            viewBox=\"0 0 {width} {height}\">"
+        )
+        .expect("Infallible");
+        if let Some(title) = &self.title {
+            out.push_str("<title>");
+            push_escaped(&mut out, title);
+            out.push_str("</title>\n");
+        }
+        write!(out, "<rect width=\"100%\" height=\"100%\" rx=\"6\"").expect("Infallible");
+        match settings.background {
+            Some(background) if AnsiColor::from(background) != AnsiColor::Default => {
+                self.write_color(&mut out, "fill", background);
+            }
+            _ => out.push_str(" fill=\"none\""),
+        }
+        out.push_str("/>\n");
+
+        if self.window_chrome {
+            for (i, fill) in ["#ff5f56", "#ffbd2e", "#27c93f"].into_iter().enumerate() {
+                let cx = px(PADDING + 40 + i as u32 * 200);
+                writeln!(
+                    out,
+                    "<circle cx=\"{cx}\" cy=\"{}\" r=\"6\" fill=\"{fill}\"/>",
+                    px(CHROME_HEIGHT / 2)
+                )
+                .expect("Infallible");
+            }
+            if let Some(title) = &self.title {
+                write!(
+                    out,
+                    "<text x=\"50%\" y=\"{}\" text-anchor=\"middle\" font-family=\"{FONT_FAMILY}\" \
+                    font-size=\"12\" fill-opacity=\"0.6\"",
+                    px(CHROME_HEIGHT / 2 + 40)
+                )
+                .expect("Infallible");
+                self.write_color(&mut out, "fill", foreground);
+                out.push('>');
+                push_escaped(&mut out, title);
+                out.push_str("</text>\n");
+            }
+        }
+
+        writeln!(
+            out,
+            "<g font-family=\"{FONT_FAMILY}\" font-size=\"{}\" xml:space=\"preserve\" \
+            style=\"white-space:pre\">",
+            px(FONT_SIZE)
+        )
+        .expect("Infallible");
+        let code_x = PADDING + gutter;
+        for (i, (tokens, _)) in lines.iter().enumerate() {
+            let line_top = top + i as u32 * LINE_HEIGHT;
+            let y = px(line_top + BASELINE);
+
+            // Backgrounds need to be drawn as separate rectangles behind the text
+            let mut column = 0;
+            for (style, text) in tokens {
+                let columns = text.chars().count() as u32;
+                if style.background != background
+                    && AnsiColor::from(style.background) != AnsiColor::Default
+                {
+                    write!(
+                        out,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"",
+                        px(code_x + column * CHAR_WIDTH),
+                        px(line_top),
+                        px(columns * CHAR_WIDTH),
+                        px(LINE_HEIGHT),
+                    )
+                    .expect("Infallible");
+                    self.write_color(&mut out, "fill", style.background);
+                    out.push_str("/>\n");
+                }
+                column += columns;
+            }
+
+            if self.line_numbers {
+                let x = px(PADDING + number_width * CHAR_WIDTH);
+                write!(out, "<text x=\"{x}\" y=\"{y}\" text-anchor=\"end\"").expect("Infallible");
+                match settings.gutter_foreground {
+                    Some(color) => self.write_color(&mut out, "fill", color),
+                    None => {
+                        self.write_color(&mut out, "fill", foreground);
+                        out.push_str(" fill-opacity=\"0.5\"");
+                    }
+                }
+                writeln!(out, ">{}</text>", i + 1).expect("Infallible");
+            }
+
+            if tokens.is_empty() {
+                continue;
+            }
+            write!(out, "<text x=\"{}\" y=\"{y}\">", px(code_x)).expect("Infallible");
+            for (style, text) in tokens {
+                out.push_str("<tspan");
+                self.write_color(&mut out, "fill", style.foreground);
+                for (flag, attr) in [
+                    (FontStyle::BOLD, " font-weight=\"bold\""),
+                    (FontStyle::ITALIC, " font-style=\"italic\""),
+                    (FontStyle::UNDERLINE, " text-decoration=\"underline\""),
+                ] {
+                    if style.font_style.contains(flag) {
+                        out.push_str(attr);
+                    }
+                }
+                out.push('>');
+                push_escaped(&mut out, text);
+                out.push_str("</tspan>");
+            }
+            out.push_str("</text>\n");
+        }
+        out.push_str("</g>\n</svg>\n");
+
+        Ok(out)
+    }
+
+    /// Writes out a color attribute (along with an opacity for translucent colors)
+    fn write_color(&self, out: &mut String, attr: &str, color: Color) {
+        let color = match AnsiColor::from(color) {
+            AnsiColor::Default => {
+                write!(out, " {attr}=\"currentColor\"").expect("Infallible");
+                return;
+            }
+            AnsiColor::Palette(index) => self.palette.color(index),
+            AnsiColor::Rgb { .. } => color,
+        };
+        let Color { r, g, b, a } = color;
+        write!(out, " {attr}=\"#{r:02x}{g:02x}{b:02x}\"").expect("Infallible");
+        if a != 0xff {
+            let opacity = f32::from(a) / 255.0;
+            write!(out, " {attr}-opacity=\"{opacity:.2}\"").expect("Infallible");
+        }
+    }
+}
+
+/// Formats a length in tenths of a pixel
+fn px(tenths: u32) -> String {
+    match tenths % 10 {
+        0 => (tenths / 10).to_string(),
+        frac => format!("{}.{frac}", tenths / 10),
+    }
+}
+
+/// Escapes `s` for XML, replacing any characters that XML doesn't allow
+fn push_escaped(out: &mut String, s: &str) {
+    let sanitized: String = s
+        .chars()
+        .map(|c| match c {
+            '\t' | '\n' | '\r' => c,
+            '\0'..='\x1f' | '\u{fffe}' | '\u{ffff}' => char::REPLACEMENT_CHARACTER,
+            _ => c,
+        })
+        .collect();
+    crate::html::push_escaped(out, &sanitized);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::theme::EmbeddedThemeName;
+
+    fn render(renderer: &Renderer, code: &str, ext: &str, theme: EmbeddedThemeName) -> String {
+        let syn_set = crate::syntax::extra_newlines();
+        let theme_set = crate::theme::extra();
+        let syntax = syn_set.find_syntax_by_extension(ext).unwrap();
+        renderer
+            .render(code, &syn_set, syntax, &theme_set[theme])
+            .unwrap()
+    }
+
+    #[test]
+    fn rust_with_everything() {
+        let renderer = Renderer::new()
+            .window_chrome(true)
+            .line_numbers(true)
+            .title("<main>.rs");
+        let code = "fn main() {\n\tlet s = \"&\";\n}\n";
+        let svg = render(&renderer, code, "rs", EmbeddedThemeName::Nord);
+        insta::assert_snapshot!(svg, @r##"
+        <svg xmlns="http://www.w3.org/2000/svg" width="191.6" height="131" viewBox="0 0 191.6 131">
+        <title>&lt;main&gt;.rs</title>
+        <rect width="100%" height="100%" rx="6" fill="#2e3440"/>
+        <circle cx="20" cy="18" r="6" fill="#ff5f56"/>
+        <circle cx="40" cy="18" r="6" fill="#ffbd2e"/>
+        <circle cx="60" cy="18" r="6" fill="#27c93f"/>
+        <text x="50%" y="22" text-anchor="middle" font-family="ui-monospace, SFMono-Regular, Menlo, Consolas, 'Liberation Mono', monospace" font-size="12" fill-opacity="0.6" fill="#d8dee9">&lt;main&gt;.rs</text>
+        <g font-family="ui-monospace, SFMono-Regular, Menlo, Consolas, 'Liberation Mono', monospace" font-size="14" xml:space="preserve" style="white-space:pre">
+        <text x="24.4" y="67" text-anchor="end" fill="#d8dee9" fill-opacity="0.40">1</text>
+        <text x="41.2" y="67"><tspan fill="#81a1c1">fn</tspan><tspan fill="#d8dee9"> </tspan><tspan fill="#88c0d0">main</tspan><tspan fill="#d8dee9">() {</tspan></text>
+        <text x="24.4" y="88" text-anchor="end" fill="#d8dee9" fill-opacity="0.40">2</text>
+        <text x="41.2" y="88"><tspan fill="#d8dee9">    </tspan><tspan fill="#81a1c1">let</tspan><tspan fill="#d8dee9"> s </tspan><tspan fill="#81a1c1">=</tspan><tspan fill="#d8dee9"> </tspan><tspan fill="#a3be8c">&quot;&amp;&quot;</tspan><tspan fill="#eceff4">;</tspan></text>
+        <text x="24.4" y="109" text-anchor="end" fill="#d8dee9" fill-opacity="0.40">3</text>
+        <text x="41.2" y="109"><tspan fill="#d8dee9">}</tspan></text>
+        </g>
+        </svg>
+        "##);
+    }
+
+    #[test]
+    fn toml_plain() {
+        let code = "[package]\nname = \"two-face\"\n";
+        let svg = render(
+            &Renderer::new(),
+            code,
+            "toml",
+            EmbeddedThemeName::InspiredGithub,
+        );
+        insta::assert_snapshot!(svg, @r##"
+        <svg xmlns="http://www.w3.org/2000/svg" width="174.8" height="74" viewBox="0 0 174.8 74">
+        <rect width="100%" height="100%" rx="6" fill="#ffffff"/>
+        <g font-family="ui-monospace, SFMono-Regular, Menlo, Consolas, 'Liberation Mono', monospace" font-size="14" xml:space="preserve" style="white-space:pre">
+        <text x="16" y="31"><tspan fill="#323232">[package]</tspan></text>
+        <text x="16" y="52"><tspan fill="#63a35c">name</tspan><tspan fill="#323232"> = </tspan><tspan fill="#183691">&quot;two-face&quot;</tspan></text>
+        </g>
+        </svg>
+        "##);
+    }
+
+    #[test]
+    fn palette_theme() {
+        let renderer = Renderer::new().palette(AnsiPalette::TANGO);
+        let code = "# hi\necho \"\x1b\"\n";
+        let svg = render(&renderer, code, "sh", EmbeddedThemeName::Base16);
+        insta::assert_snapshot!(svg, @r##"
+        <svg xmlns="http://www.w3.org/2000/svg" width="99.2" height="74" viewBox="0 0 99.2 74">
+        <rect width="100%" height="100%" rx="6" fill="#2e3436"/>
+        <g font-family="ui-monospace, SFMono-Regular, Menlo, Consolas, 'Liberation Mono', monospace" font-size="14" xml:space="preserve" style="white-space:pre">
+        <text x="16" y="31"><tspan fill="#555753"># hi</tspan></text>
+        <text x="16" y="52"><tspan fill="#06989a">echo</tspan><tspan fill="#d3d7cf"> &quot;</tspan><tspan fill="#4e9a06">�</tspan><tspan fill="#d3d7cf">&quot;</tspan></text>
+        </g>
+        </svg>
+        "##);
+        let svg = render(&Renderer::new(), code, "sh", EmbeddedThemeName::Ansi);
+        insta::assert_snapshot!(svg, @r##"
+        <svg xmlns="http://www.w3.org/2000/svg" width="99.2" height="74" viewBox="0 0 99.2 74">
+        <rect width="100%" height="100%" rx="6" fill="none"/>
+        <g font-family="ui-monospace, SFMono-Regular, Menlo, Consolas, 'Liberation Mono', monospace" font-size="14" xml:space="preserve" style="white-space:pre">
+        <text x="16" y="31"><tspan fill="#00cd00"># hi</tspan></text>
+        <text x="16" y="52"><tspan fill="#00cdcd">echo</tspan><tspan fill="currentColor"> </tspan><tspan fill="#00cd00">&quot;�&quot;</tspan></text>
+        </g>
+        </svg>
+        "##);
+    }
+
+    #[test]
+    fn no_theme_background() {
+        let syn_set = crate::syntax::extra_newlines();
+        let mut theme = crate::theme::extra()[EmbeddedThemeName::Nord].clone();
+        theme.settings.background = None;
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        let svg = Renderer::new()
+            .render("fn main() {}\n", &syn_set, syntax, &theme)
+            .unwrap();
+        assert!(svg.contains("fill=\"none\""), "{svg}");
+        // Tokens on syntect's fallback background don't get their own rectangles
+        assert!(!svg.contains("<rect x="), "{svg}");
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(px(0), "0");
+        assert_eq!(px(84), "8.4");
+        assert_eq!(px(210), "21");
+    }
+}
@@ -0,0 +1,753 @@
+//! Renders highlighted text as terminal escape sequences
+//!
+//! This is similar to [`syntect::util::as_24_bit_terminal_escaped()`], but it understands the
+//! color encoding used by the terminal-palette themes (`EmbeddedThemeName::{Ansi, Base16,
+//! Base16_256}`). These themes store their colors as `#RRGGBBAA` where
+//!
+//! - An alpha of `00` means that the red channel holds a palette index that's displayed using
+//!   whatever colors the user configured for their terminal
+//! - An alpha of `01` means that the terminal's default foreground/background is used
+//!
+//! Any other color is emitted as a regular truecolor escape sequence. These are the same rules
+//! that [`bat`](https://github.com/sharkdp/bat) uses
+//!
+//! # Example
+//!
+//! ```
+//! use two_face::{terminal, theme::EmbeddedThemeName};
+//! use syntect::{easy::HighlightLines, util::LinesWithEndings};
+//!
+//! let syn_set = two_face::syntax::extra_newlines();
+//! let theme_set = two_face::theme::extra();
+//!
+//! let syntax = syn_set.find_syntax_by_extension("toml").unwrap();
+//! let mut highlighter = HighlightLines::new(syntax, &theme_set[EmbeddedThemeName::Base16]);
+//! for line in LinesWithEndings::from("[section]\nkey = 123\n") {
+//!     let ranges = highlighter.highlight_line(line, &syn_set).unwrap();
+//!     print!("{}", terminal::as_terminal_escaped(&ranges));
+//! }
+//! ```
+
+use std::{env, fmt::Write};
+
+use syntect::highlighting::{Color, FontStyle, Style, StyleModifier, Theme};
+
+use crate::color;
+
+/// A color as understood by a terminal
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum AnsiColor {
+    /// The terminal's default foreground or background color
+    Default,
+    /// An index into the terminal's palette
+    ///
+    /// `0..=7` are the basic colors, `8..=15` are their bright variants, and the rest follow the
+    /// xterm 256-color palette
+    Palette(u8),
+    /// A 24-bit color
+    Rgb { r: u8, g: u8, b: u8 },
+}
+
+impl From<Color> for AnsiColor {
+    fn from(color: Color) -> Self {
+        match color.a {
+            0 => Self::Palette(color.r),
+            1 => Self::Default,
+            _ => Self::Rgb {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+            },
+        }
+    }
+}
+
+impl From<AnsiColor> for Color {
+    /// Encodes the color in the same way as the terminal-palette themes
+    fn from(color: AnsiColor) -> Self {
+        match color {
+            AnsiColor::Default => Self {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 1,
+            },
+            AnsiColor::Palette(index) => Self {
+                r: index,
+                g: 0,
+                b: 0,
+                a: 0,
+            },
+            AnsiColor::Rgb { r, g, b } => Self { r, g, b, a: 0xff },
+        }
+    }
+}
+
+impl AnsiColor {
+    /// Reduces the color to something that can be displayed with `depth`
+    ///
+    /// Truecolor values are matched to the perceptually closest palette entry. The first 16
+    /// palette entries are configurable by the user, so they're only used as targets when
+    /// downsampling to [`ColorDepth::Ansi16`] where colors are matched by hue and lightness
+    /// (assuming xterm's default colors)
+    ///
+    /// ```
+    /// use two_face::terminal::{AnsiColor, ColorDepth};
+    ///
+    /// let red = AnsiColor::Rgb { r: 0xff, g: 0, b: 0 };
+    /// assert_eq!(red.downsample(ColorDepth::TrueColor), red);
+    /// assert_eq!(red.downsample(ColorDepth::Ansi256), AnsiColor::Palette(196));
+    /// assert_eq!(red.downsample(ColorDepth::Ansi16), AnsiColor::Palette(9));
+    /// ```
+    pub fn downsample(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (_, ColorDepth::TrueColor) | (Self::Default, _) => self,
+            (Self::Palette(_), ColorDepth::Ansi256) | (Self::Palette(0..=15), _) => self,
+            (Self::Palette(index), ColorDepth::Ansi16) => {
+                Self::Palette(color::nearest_ansi16(color::xterm_rgb(index)))
+            }
+            (Self::Rgb { r, g, b }, _) => {
+                let rgb = Color { r, g, b, a: 0xff };
+                let index = match depth {
+                    ColorDepth::Ansi16 => color::nearest_ansi16(rgb),
+                    _ => color::nearest_ansi256(rgb),
+                };
+                Self::Palette(index)
+            }
+        }
+    }
+
+    fn write_params(self, params: &mut String, layer: Layer, depth: ColorDepth) {
+        let base = match layer {
+            Layer::Foreground => 30,
+            Layer::Background => 40,
+        };
+        match self.downsample(depth) {
+            Self::Default => {}
+            // The first 8 colors get the classic codes which are supported everywhere
+            Self::Palette(index @ 0..=7) => push_param(params, base + u32::from(index)),
+            // Terminals limited to 16 colors may not support the 256-color codes, so use the
+            // bright variants' codes instead
+            Self::Palette(index @ 8..=15) if depth == ColorDepth::Ansi16 => {
+                push_param(params, base + 60 + u32::from(index - 8))
+            }
+            Self::Palette(index) => {
+                push_param(params, base + 8);
+                write!(params, ";5;{index}").expect("Infallible");
+            }
+            Self::Rgb { r, g, b } => {
+                push_param(params, base + 8);
+                write!(params, ";2;{r};{g};{b}").expect("Infallible");
+            }
+        }
+    }
+}
+
+/// The range of colors that a terminal can display
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// 24-bit colors
+    #[default]
+    TrueColor,
+    /// The xterm 256-color palette
+    Ansi256,
+    /// The basic 8 colors along with their bright variants
+    Ansi16,
+}
+
+impl ColorDepth {
+    /// Detects the color depth supported by the current terminal
+    ///
+    /// This is based on the `COLORTERM` and `TERM` environment variables. See
+    /// [`ColorDepth::from_env_vars()`] for more details
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").ok();
+        let term = env::var("TERM").ok();
+        Self::from_env_vars(colorterm.as_deref(), term.as_deref())
+    }
+
+    /// Determines the color depth from the values of the `COLORTERM` and `TERM` environment
+    /// variables
+    ///
+    /// A `COLORTERM` of `truecolor` or `24bit`, or a `TERM` ending in `-direct` means truecolor
+    /// support. Otherwise a `TERM` mentioning `256color` means 256 colors. Anything else falls
+    /// back to the basic 16 colors
+    ///
+    /// ```
+    /// use two_face::terminal::ColorDepth;
+    ///
+    /// assert_eq!(
+    ///     ColorDepth::from_env_vars(Some("truecolor"), Some("xterm-256color")),
+    ///     ColorDepth::TrueColor,
+    /// );
+    /// assert_eq!(
+    ///     ColorDepth::from_env_vars(None, Some("screen-256color")),
+    ///     ColorDepth::Ansi256,
+    /// );
+    /// assert_eq!(ColorDepth::from_env_vars(None, Some("xterm")), ColorDepth::Ansi16);
+    /// assert_eq!(ColorDepth::from_env_vars(None, None), ColorDepth::Ansi16);
+    /// ```
+    pub fn from_env_vars(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let colorterm = colorterm.unwrap_or_default();
+        let term = term.unwrap_or_default();
+        if matches!(colorterm, "truecolor" | "24bit") || term.ends_with("-direct") {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// How to display a font style that isn't supported
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum FontFallback {
+    /// Drop the font style entirely
+    Drop,
+    /// Swap it for other font styles (which are dropped too if they're not supported either)
+    Replace(FontStyle),
+    /// Brighten the foreground color instead
+    Brighten,
+    /// Dim the foreground color instead
+    Dim,
+}
+
+/// The set of font styles that can be displayed along with fallbacks for the rest
+///
+/// Many terminals and log viewers don't display every font style correctly (e.g. some render
+/// italics as reverse video). This can be used with either [`Renderer::font_capabilities()`] or
+/// [`crate::theme::downgrade_font_styles()`] to map unsupported font styles to something else
+///
+/// # Example
+///
+/// ```
+/// use two_face::terminal::{FontCapabilities, FontFallback};
+/// use syntect::highlighting::{Color, FontStyle, Style};
+///
+/// // Drop italics and display underlines as bold
+/// let caps = FontCapabilities::new(FontStyle::BOLD)
+///     .fallback(FontStyle::UNDERLINE, FontFallback::Replace(FontStyle::BOLD));
+///
+/// let style = Style {
+///     font_style: FontStyle::ITALIC | FontStyle::UNDERLINE,
+///     ..Style::default()
+/// };
+/// assert_eq!(caps.apply(style).font_style, FontStyle::BOLD);
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct FontCapabilities {
+    supported: FontStyle,
+    bold: FontFallback,
+    italic: FontFallback,
+    underline: FontFallback,
+}
+
+impl Default for FontCapabilities {
+    /// Supports every font style
+    fn default() -> Self {
+        Self::new(FontStyle::all())
+    }
+}
+
+impl FontCapabilities {
+    /// Supports the font styles in `supported` dropping all of the others
+    pub fn new(supported: FontStyle) -> Self {
+        Self {
+            supported,
+            bold: FontFallback::Drop,
+            italic: FontFallback::Drop,
+            underline: FontFallback::Drop,
+        }
+    }
+
+    /// Sets the fallback used for each unsupported font style in `styles`
+    pub fn fallback(mut self, styles: FontStyle, fallback: FontFallback) -> Self {
+        if styles.contains(FontStyle::BOLD) {
+            self.bold = fallback;
+        }
+        if styles.contains(FontStyle::ITALIC) {
+            self.italic = fallback;
+        }
+        if styles.contains(FontStyle::UNDERLINE) {
+            self.underline = fallback;
+        }
+        self
+    }
+
+    /// The font styles that are supported
+    pub fn supported(&self) -> FontStyle {
+        self.supported
+    }
+
+    /// Downgrades any unsupported font styles in `style`
+    pub fn apply(&self, style: Style) -> Style {
+        let modifier = self.apply_modifier(StyleModifier {
+            foreground: Some(style.foreground),
+            background: Some(style.background),
+            font_style: Some(style.font_style),
+        });
+        style.apply(modifier)
+    }
+
+    /// Like [`FontCapabilities::apply()`], but for a theme's [`StyleModifier`]s
+    ///
+    /// [`FontFallback::Brighten`] and [`FontFallback::Dim`] can only be applied when the modifier
+    /// sets its own foreground color
+    pub fn apply_modifier(&self, mut modifier: StyleModifier) -> StyleModifier {
+        let Some(font_style) = modifier.font_style else {
+            return modifier;
+        };
+
+        let mut downgraded = font_style & self.supported;
+        for (flag, fallback) in [
+            (FontStyle::BOLD, self.bold),
+            (FontStyle::ITALIC, self.italic),
+            (FontStyle::UNDERLINE, self.underline),
+        ] {
+            if !font_style.contains(flag) || self.supported.contains(flag) {
+                continue;
+            }
+
+            match fallback {
+                FontFallback::Drop => {}
+                FontFallback::Replace(styles) => downgraded |= styles & self.supported,
+                FontFallback::Brighten => {
+                    modifier.foreground = modifier.foreground.map(|c| shift_brightness(c, true));
+                }
+                FontFallback::Dim => {
+                    modifier.foreground = modifier.foreground.map(|c| shift_brightness(c, false));
+                }
+            }
+        }
+        modifier.font_style = Some(downgraded);
+        modifier
+    }
+}
+
+/// Brightens or dims a color while keeping the palette encoding intact
+///
+/// The basic palette colors swap to and from their bright variants while truecolor values get
+/// mixed with white or black
+fn shift_brightness(color: Color, brighten: bool) -> Color {
+    match AnsiColor::from(color) {
+        AnsiColor::Palette(index @ 0..=7) if brighten => AnsiColor::Palette(index + 8).into(),
+        AnsiColor::Palette(index @ 8..=15) if !brighten => AnsiColor::Palette(index - 8).into(),
+        AnsiColor::Palette(_) | AnsiColor::Default => color,
+        AnsiColor::Rgb { .. } => {
+            let target: u8 = if brighten { 0xff } else { 0x00 };
+            let mix = |channel: u8| {
+                let mixed = u16::from(channel) * 2 / 3 + u16::from(target) / 3;
+                mixed as u8
+            };
+            Color {
+                r: mix(color.r),
+                g: mix(color.g),
+                b: mix(color.b),
+                a: color.a,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Layer {
+    Foreground,
+    Background,
+}
+
+fn push_param(params: &mut String, param: u32) {
+    if !params.is_empty() {
+        params.push(';');
+    }
+    write!(params, "{param}").expect("Infallible");
+}
+
+/// Converts highlighted ranges into terminal escape sequences
+///
+/// # Example
+///
+/// ```
+/// use two_face::terminal::Renderer;
+/// use syntect::highlighting::{Color, FontStyle, Style};
+///
+/// let red_on_default = Style {
+///     // Palette index 1 aka red
+///     foreground: Color { r: 1, g: 0, b: 0, a: 0 },
+///     // The terminal's default background
+///     background: Color { r: 0, g: 0, b: 0, a: 1 },
+///     font_style: FontStyle::BOLD,
+/// };
+/// let rendered = Renderer::new().render(&[(red_on_default, "fn")]);
+/// assert_eq!(rendered, "\x1b[1;31mfn\x1b[0m");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Renderer {
+    color_depth: ColorDepth,
+    font_capabilities: FontCapabilities,
+    theme_background: Option<Color>,
+    background: bool,
+}
+
+impl Renderer {
+    /// Creates a renderer with the default settings
+    ///
+    /// By default colors are emitted as truecolor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Downsamples colors to fit within `depth`
+    ///
+    /// See [`AnsiColor::downsample()`] for how colors get mapped. Use [`ColorDepth::detect()`] to
+    /// match whatever the current terminal supports
+    ///
+    /// ```
+    /// use two_face::terminal::{ColorDepth, Renderer};
+    ///
+    /// let renderer = Renderer::new().color_depth(ColorDepth::detect());
+    /// ```
+    pub fn color_depth(mut self, depth: ColorDepth) -> Self {
+        self.color_depth = depth;
+        self
+    }
+
+    /// Downgrades any font styles that aren't supported by `capabilities`
+    ///
+    /// ```
+    /// use two_face::terminal::{FontCapabilities, Renderer};
+    /// use syntect::highlighting::FontStyle;
+    ///
+    /// // Avoid italics which some terminals display as reverse video
+    /// let no_italics = FontCapabilities::new(FontStyle::BOLD | FontStyle::UNDERLINE);
+    /// let renderer = Renderer::new().font_capabilities(no_italics);
+    /// ```
+    pub fn font_capabilities(mut self, capabilities: FontCapabilities) -> Self {
+        self.font_capabilities = capabilities;
+        self
+    }
+
+    /// The theme that the ranges were highlighted with
+    ///
+    /// Knowing the theme's background lets scopes with their own background (e.g. `invalid`)
+    /// still get painted while the theme's background is left up to the terminal. Without a theme
+    /// every background is treated as the theme's
+    ///
+    /// ```
+    /// use two_face::{terminal::Renderer, theme::{extra, EmbeddedThemeName}};
+    ///
+    /// let theme_set = extra();
+    /// let renderer = Renderer::new().theme(&theme_set[EmbeddedThemeName::Nord]);
+    /// ```
+    pub fn theme(mut self, theme: &Theme) -> Self {
+        // Matches the background that syntect falls back to
+        self.theme_background = Some(theme.settings.background.unwrap_or(Color::WHITE));
+        self
+    }
+
+    /// Whether to paint the theme's background behind every token (disabled by default)
+    ///
+    /// By default the theme's background is left up to the terminal like
+    /// [`crate::theme::without_background()`] does, which avoids it clashing with the user's own
+    /// background. Backgrounds that differ from the theme's (see [`Renderer::theme()`]) are
+    /// always emitted
+    ///
+    /// ```
+    /// use two_face::terminal::Renderer;
+    /// use syntect::highlighting::{Color, Style};
+    ///
+    /// let style = Style {
+    ///     foreground: Color { r: 0xff, g: 0x00, b: 0x00, a: 0xff },
+    ///     background: Color { r: 0x00, g: 0x00, b: 0x00, a: 0xff },
+    ///     ..Style::default()
+    /// };
+    /// let rendered = Renderer::new().render(&[(style, "fn")]);
+    /// assert_eq!(rendered, "\x1b[38;2;255;0;0mfn\x1b[0m");
+    /// let rendered = Renderer::new().background(true).render(&[(style, "fn")]);
+    /// assert_eq!(rendered, "\x1b[38;2;255;0;0;48;2;0;0;0mfn\x1b[0m");
+    /// ```
+    pub fn background(mut self, enabled: bool) -> Self {
+        self.background = enabled;
+        self
+    }
+
+    /// Renders a line's worth of highlighted ranges
+    ///
+    /// Each styled range is reset at its end, and trailing newlines are kept outside of the
+    /// styled region, so that colors never leak past the rendered text
+    pub fn render(&self, ranges: &[(Style, &str)]) -> String {
+        let mut out = String::new();
+        self.render_into(ranges, &mut out);
+        out
+    }
+
+    /// Like [`Renderer::render()`], but appends to an existing buffer
+    pub fn render_into(&self, ranges: &[(Style, &str)], out: &mut String) {
+        let mut params = String::new();
+        for &(style, text) in ranges {
+            params.clear();
+            self.write_params(style, &mut params);
+
+            let content = text.trim_end_matches(['\r', '\n']);
+            let line_ending = &text[content.len()..];
+            if params.is_empty() || content.is_empty() {
+                out.push_str(text);
+            } else {
+                write!(out, "\x1b[{params}m{content}\x1b[0m{line_ending}").expect("Infallible");
+            }
+        }
+    }
+
+    fn write_params(&self, style: Style, params: &mut String) {
+        let style = self.font_capabilities.apply(style);
+        for (flag, param) in [
+            (FontStyle::BOLD, 1),
+            (FontStyle::ITALIC, 3),
+            (FontStyle::UNDERLINE, 4),
+        ] {
+            if style.font_style.contains(flag) {
+                push_param(params, param);
+            }
+        }
+        let depth = self.color_depth;
+        AnsiColor::from(style.foreground).write_params(params, Layer::Foreground, depth);
+        let is_theme_background = self
+            .theme_background
+            .map_or(true, |background| style.background == background);
+        if self.background || !is_theme_background {
+            AnsiColor::from(style.background).write_params(params, Layer::Background, depth);
+        }
+    }
+}
+
+/// Renders highlighted ranges using a default [`Renderer`]
+pub fn as_terminal_escaped(ranges: &[(Style, &str)]) -> String {
+    Renderer::new().render(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::theme::EmbeddedThemeName;
+
+    use syntect::{easy::HighlightLines, util::LinesWithEndings};
+
+    const SAMPLE_RUST: &str = "fn main() {\n    let x = 1;\n}\n";
+
+    fn sample_escaped(name: EmbeddedThemeName, renderer: &Renderer) -> String {
+        let theme_set = crate::theme::extra();
+        render_sample(theme_set.get(name), renderer)
+    }
+
+    fn render_sample(theme: &Theme, renderer: &Renderer) -> String {
+        let syn_set = crate::syntax::extra_newlines();
+        let syntax = syn_set.find_syntax_by_extension("rs").unwrap();
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let renderer = renderer.clone().theme(theme);
+        let mut out = String::new();
+        for line in LinesWithEndings::from(SAMPLE_RUST) {
+            let ranges = highlighter.highlight_line(line, &syn_set).unwrap();
+            renderer.render_into(&ranges, &mut out);
+        }
+        // Make the escapes readable in the snapshots
+        out.replace('\x1b', "\\e")
+    }
+
+    #[test]
+    fn color_roundtrip() {
+        for color in [
+            AnsiColor::Default,
+            AnsiColor::Palette(3),
+            AnsiColor::Palette(200),
+            AnsiColor::Rgb { r: 1, g: 2, b: 3 },
+        ] {
+            assert_eq!(AnsiColor::from(Color::from(color)), color);
+        }
+    }
+
+    #[test]
+    fn downsample() {
+        let gray = AnsiColor::Rgb {
+            r: 0x80,
+            g: 0x80,
+            b: 0x80,
+        };
+        assert_eq!(
+            gray.downsample(ColorDepth::Ansi256),
+            AnsiColor::Palette(244)
+        );
+        assert_eq!(gray.downsample(ColorDepth::Ansi16), AnsiColor::Palette(8));
+
+        // Palette colors only get remapped when they're outside of the supported range
+        let orange = AnsiColor::Palette(208);
+        assert_eq!(orange.downsample(ColorDepth::Ansi256), orange);
+        assert_eq!(orange.downsample(ColorDepth::Ansi16), AnsiColor::Palette(9));
+        let red = AnsiColor::Palette(1);
+        assert_eq!(red.downsample(ColorDepth::Ansi16), red);
+        assert_eq!(
+            AnsiColor::Default.downsample(ColorDepth::Ansi16),
+            AnsiColor::Default
+        );
+    }
+
+    #[test]
+    fn font_fallbacks() {
+        let style = |font_style, foreground| Style {
+            foreground,
+            background: AnsiColor::Default.into(),
+            font_style,
+        };
+        let red = AnsiColor::Palette(1).into();
+        let bright_red = AnsiColor::Palette(9).into();
+        let gray = AnsiColor::Rgb {
+            r: 0x90,
+            g: 0x90,
+            b: 0x90,
+        }
+        .into();
+
+        let all = FontCapabilities::default();
+        let italic_bold = style(FontStyle::ITALIC | FontStyle::BOLD, red);
+        assert_eq!(all.apply(italic_bold), italic_bold);
+
+        let none = FontCapabilities::new(FontStyle::empty());
+        assert_eq!(none.apply(italic_bold), style(FontStyle::empty(), red));
+
+        let underline_as_bold = FontCapabilities::new(FontStyle::BOLD)
+            .fallback(FontStyle::UNDERLINE, FontFallback::Replace(FontStyle::BOLD));
+        assert_eq!(
+            underline_as_bold.apply(style(FontStyle::UNDERLINE, red)),
+            style(FontStyle::BOLD, red)
+        );
+        // Replacements have to be supported too
+        let underline_as_italic = FontCapabilities::new(FontStyle::BOLD).fallback(
+            FontStyle::UNDERLINE,
+            FontFallback::Replace(FontStyle::ITALIC),
+        );
+        assert_eq!(
+            underline_as_italic.apply(style(FontStyle::UNDERLINE, red)),
+            style(FontStyle::empty(), red)
+        );
+
+        let bold_as_bright = FontCapabilities::new(FontStyle::empty())
+            .fallback(FontStyle::BOLD, FontFallback::Brighten);
+        assert_eq!(
+            bold_as_bright.apply(style(FontStyle::BOLD, red)),
+            style(FontStyle::empty(), bright_red)
+        );
+        let brighter_gray = bold_as_bright
+            .apply(style(FontStyle::BOLD, gray))
+            .foreground;
+        assert_eq!(
+            AnsiColor::from(brighter_gray),
+            AnsiColor::Rgb {
+                r: 0xb5,
+                g: 0xb5,
+                b: 0xb5,
+            }
+        );
+
+        let italic_as_dim = FontCapabilities::new(FontStyle::empty())
+            .fallback(FontStyle::ITALIC, FontFallback::Dim);
+        assert_eq!(
+            italic_as_dim.apply(style(FontStyle::ITALIC, bright_red)),
+            style(FontStyle::empty(), red)
+        );
+    }
+
+    #[test]
+    fn background() {
+        let theme_set = crate::theme::extra();
+        let nord = &theme_set[EmbeddedThemeName::Nord];
+        let renderer = Renderer::new().background(true);
+        let with_bg = sample_escaped(EmbeddedThemeName::Nord, &renderer);
+        let without_bg = sample_escaped(EmbeddedThemeName::Nord, &Renderer::new());
+        assert!(with_bg.contains("48;2;46;52;64"));
+        assert!(!without_bg.contains("48;"));
+
+        // Stripping the theme's background has the same effect on the output
+        let transparent = crate::theme::without_background(nord);
+        assert_eq!(render_sample(&transparent, &renderer), without_bg);
+
+        // Scopes with their own background keep it
+        let invalid = Style {
+            background: Color {
+                r: 0xbf,
+                g: 0x61,
+                b: 0x6a,
+                a: 0xff,
+            },
+            ..Style::default()
+        };
+        let rendered = Renderer::new().theme(nord).render(&[(invalid, "x")]);
+        assert!(rendered.contains("48;2;191;97;106"));
+        assert!(!Renderer::new().render(&[(invalid, "x")]).contains("48;"));
+    }
+
+    #[test]
+    fn ansi() {
+        insta::assert_snapshot!(
+            sample_escaped(EmbeddedThemeName::Ansi, &Renderer::new()),
+            @r"
+        \e[35mfn\e[0m \e[34mmain\e[0m() {
+            \e[35mlet\e[0m x \e[35m=\e[0m \e[33m1\e[0m;
+        }
+        "
+        );
+    }
+
+    #[test]
+    fn base16_256() {
+        insta::assert_snapshot!(
+            sample_escaped(EmbeddedThemeName::Base16_256, &Renderer::new()),
+            @r"
+        \e[35mfn\e[0m\e[37m \e[0m\e[34mmain\e[0m\e[37m(\e[0m\e[37m)\e[0m\e[37m \e[0m\e[37m{\e[0m
+        \e[37m    \e[0m\e[35mlet\e[0m\e[37m x \e[0m\e[37m=\e[0m\e[37m \e[0m\e[38;5;16m1\e[0m\e[37m;\e[0m
+        \e[37m}\e[0m
+        "
+        );
+    }
+
+    #[test]
+    fn truecolor() {
+        insta::assert_snapshot!(
+            sample_escaped(EmbeddedThemeName::Nord, &Renderer::new()),
+            @r"
+        \e[38;2;129;161;193mfn\e[0m\e[38;2;216;222;233m \e[0m\e[38;2;136;192;208mmain\e[0m\e[38;2;216;222;233m(\e[0m\e[38;2;216;222;233m)\e[0m\e[38;2;216;222;233m \e[0m\e[38;2;216;222;233m{\e[0m
+        \e[38;2;216;222;233m    \e[0m\e[38;2;129;161;193mlet\e[0m\e[38;2;216;222;233m x \e[0m\e[38;2;129;161;193m=\e[0m\e[38;2;216;222;233m \e[0m\e[38;2;180;142;173m1\e[0m\e[38;2;236;239;244m;\e[0m
+        \e[38;2;216;222;233m}\e[0m
+        "
+        );
+    }
+    #[test]
+    fn ansi256() {
+        let renderer = Renderer::new().color_depth(ColorDepth::Ansi256);
+        insta::assert_snapshot!(sample_escaped(EmbeddedThemeName::Nord, &renderer), @r"
+        \e[38;5;110mfn\e[0m\e[38;5;253m \e[0m\e[38;5;110mmain\e[0m\e[38;5;253m(\e[0m\e[38;5;253m)\e[0m\e[38;5;253m \e[0m\e[38;5;253m{\e[0m
+        \e[38;5;253m    \e[0m\e[38;5;110mlet\e[0m\e[38;5;253m x \e[0m\e[38;5;110m=\e[0m\e[38;5;253m \e[0m\e[38;5;139m1\e[0m\e[38;5;255m;\e[0m
+        \e[38;5;253m}\e[0m
+        ");
+    }
+
+    #[test]
+    fn ansi16() {
+        let renderer = Renderer::new().color_depth(ColorDepth::Ansi16);
+        insta::assert_snapshot!(sample_escaped(EmbeddedThemeName::Nord, &renderer), @r"
+        \e[94mfn\e[0m\e[37m \e[0m\e[36mmain\e[0m\e[37m(\e[0m\e[37m)\e[0m\e[37m \e[0m\e[37m{\e[0m
+        \e[37m    \e[0m\e[94mlet\e[0m\e[37m x \e[0m\e[94m=\e[0m\e[37m \e[0m\e[95m1\e[0m\e[97m;\e[0m
+        \e[37m}\e[0m
+        ");
+
+        // Bright palette colors use the dedicated 16-color codes
+        let bright_black_on_default = Style {
+            foreground: AnsiColor::Palette(8).into(),
+            background: AnsiColor::Default.into(),
+            font_style: FontStyle::empty(),
+        };
+        assert_eq!(
+            renderer.render(&[(bright_black_on_default, "// hi")]),
+            "\x1b[90m// hi\x1b[0m"
+        );
+    }
+}
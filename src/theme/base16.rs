@@ -0,0 +1,454 @@
+//! Builds themes from [Base16](https://github.com/tinted-theming/home) color schemes
+//!
+//! Base16 schemes are just a palette of 16 colors where `base00` through `base07` go from the
+//! darkest to the lightest shade (for dark schemes) and `base08` through `base0F` are the accent
+//! colors. [`from_scheme()`] maps the palette onto the same scopes as the embedded `Base16*`
+//! themes, so any scheme becomes a full syntax theme
+//!
+//! Base24 schemes are supported too. Their extra `base10` through `base17` colors are kept in
+//! [`Base16Scheme::base24`], but the theme only uses the Base16 subset which is what Base24 schemes
+//! are designed to fall back to
+//!
+//! # Example
+//!
+//! ```
+//! use two_face::theme::base16::{self, Base16Scheme};
+//!
+//! let scheme: Base16Scheme = r#"
+//! scheme: "Eighties"
+//! author: "Chris Kempson (http://chriskempson.com)"
+//! base00: "2d2d2d"
+//! base01: "393939"
+//! base02: "515151"
+//! base03: "747369"
+//! base04: "a09f93"
+//! base05: "d3d0c8"
+//! base06: "e8e6df"
+//! base07: "f2f0ec"
+//! base08: "f2777a"
+//! base09: "f99157"
+//! base0A: "ffcc66"
+//! base0B: "99cc99"
+//! base0C: "66cccc"
+//! base0D: "6699cc"
+//! base0E: "cc99cc"
+//! base0F: "d27b53"
+//! "#.parse().unwrap();
+//! let theme = base16::from_scheme(&scheme);
+//! assert_eq!(theme.name.as_deref(), Some("Eighties"));
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use syntect::highlighting::{
+    Color, FontStyle, ScopeSelectors, StyleModifier, Theme, ThemeItem, ThemeSettings,
+};
+
+use crate::color::rgb;
+use Paint::{Base, Fixed};
+
+/// A Base16 (or Base24) color scheme
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Base16Scheme {
+    pub name: String,
+    pub author: Option<String>,
+    /// `base00` through `base0F`
+    pub palette: [Color; 16],
+    /// `base10` through `base17` for Base24 schemes
+    pub base24: Option<[Color; 8]>,
+}
+
+impl FromStr for Base16Scheme {
+    type Err = ParseSchemeError;
+
+    /// Parses the YAML scheme format
+    ///
+    /// Both the original format with all of the keys at the top level and the newer format with
+    /// the colors nested under `palette` are supported. Colors can be written with or without the
+    /// leading `#`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut author = None;
+        let mut colors: [Option<Color>; 24] = [None; 24];
+        let mut in_palette = false;
+        for line in s.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = trimmed.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), yaml_scalar(value));
+            let nested = trimmed.len() != line.len();
+            if !nested {
+                in_palette = key == "palette";
+            }
+
+            if let Some(index) = base_index(key) {
+                if !nested || in_palette {
+                    let color =
+                        parse_color(value).ok_or_else(|| ParseSchemeError::InvalidColor {
+                            key: key.to_owned(),
+                            value: value.to_owned(),
+                        })?;
+                    colors[index] = Some(color);
+                }
+            } else if !nested {
+                match key {
+                    // `scheme` is the original format's name for it
+                    "scheme" | "name" => name = Some(value.to_owned()),
+                    "author" if !value.is_empty() => author = Some(value.to_owned()),
+                    _ => {}
+                }
+            }
+        }
+
+        let name = name.ok_or_else(|| ParseSchemeError::MissingKey("scheme".to_owned()))?;
+        let color = |index: usize| {
+            colors[index].ok_or_else(|| ParseSchemeError::MissingKey(base_name(index)))
+        };
+        let mut palette = [Color::BLACK; 16];
+        for (index, slot) in palette.iter_mut().enumerate() {
+            *slot = color(index)?;
+        }
+        let base24 = if colors[16..].iter().any(Option::is_some) {
+            let mut base24 = [Color::BLACK; 8];
+            for (index, slot) in base24.iter_mut().enumerate() {
+                *slot = color(16 + index)?;
+            }
+            Some(base24)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name,
+            author,
+            palette,
+            base24,
+        })
+    }
+}
+
+/// An error from parsing a [`Base16Scheme`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseSchemeError {
+    /// A required key like `scheme` or `base0A` is missing
+    MissingKey(String),
+    /// A color isn't a 6 digit hex color
+    InvalidColor { key: String, value: String },
+}
+
+impl fmt::Display for ParseSchemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingKey(key) => write!(f, "missing `{key}`"),
+            Self::InvalidColor { key, value } => write!(f, "invalid color for `{key}`: `{value}`"),
+        }
+    }
+}
+
+impl std::error::Error for ParseSchemeError {}
+
+/// Strips quotes or a trailing comment from a YAML scalar
+fn yaml_scalar(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(quoted) = value.strip_prefix(quote) {
+            return quoted.split_once(quote).map_or(quoted, |(inner, _)| inner);
+        }
+    }
+    value
+        .split_once(" #")
+        .map_or(value, |(value, _)| value)
+        .trim()
+}
+
+/// Maps `base00` through `base17` to their palette index
+fn base_index(key: &str) -> Option<usize> {
+    let hex = key.strip_prefix("base")?;
+    if hex.len() != 2 {
+        return None;
+    }
+    let index = usize::from_str_radix(hex, 16).ok()?;
+    (index < 24).then_some(index)
+}
+
+fn base_name(index: usize) -> String {
+    format!("base{index:02X}")
+}
+
+fn parse_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    let [_, r, g, b] = rgb.to_be_bytes();
+    Some(Color { r, g, b, a: 0xff })
+}
+
+/// Where a rule's color comes from
+#[derive(Clone, Copy)]
+enum Paint {
+    Base(usize),
+    Fixed(Color),
+}
+
+struct Rule {
+    scope: &'static str,
+    foreground: Paint,
+    background: Option<Paint>,
+    font_style: Option<FontStyle>,
+}
+
+const fn rule(scope: &'static str, foreground: Paint) -> Rule {
+    Rule {
+        scope,
+        foreground,
+        background: None,
+        font_style: None,
+    }
+}
+
+impl Rule {
+    const fn background(mut self, background: Paint) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    const fn font_style(mut self, font_style: FontStyle) -> Self {
+        self.font_style = Some(font_style);
+        self
+    }
+}
+
+/// The standard Base16 tmTheme template that e.g. the embedded Eighties and Ocean themes come from
+const RULES: &[Rule] = &[
+    rule("variable.parameter.function", Base(0x05)),
+    rule("comment, punctuation.definition.comment", Base(0x03)),
+    rule(
+        "punctuation.definition.string, punctuation.definition.variable, \
+        punctuation.definition.string, punctuation.definition.parameters, \
+        punctuation.definition.string, punctuation.definition.array",
+        Base(0x05),
+    ),
+    rule("none", Base(0x05)),
+    rule("keyword.operator", Base(0x05)),
+    rule("keyword", Base(0x0E)),
+    rule("variable, variable.other.dollar.only.js", Base(0x08)),
+    rule(
+        "entity.name.function, meta.require, support.function.any-method, variable.function",
+        Base(0x0D),
+    ),
+    rule(
+        "support.class, entity.name.class, entity.name.type.class",
+        Base(0x0A),
+    ),
+    rule("meta.class", Base(0x07)),
+    rule("keyword.other.special-method", Base(0x0D)),
+    rule("storage", Base(0x0E)),
+    rule("support.function", Base(0x0C)),
+    rule(
+        "string, constant.other.symbol, entity.other.inherited-class",
+        Base(0x0B),
+    ),
+    rule("constant.numeric", Base(0x09)),
+    rule("none", Base(0x09)),
+    rule("none", Base(0x09)),
+    rule("constant", Base(0x09)),
+    rule("entity.name.tag", Base(0x08)),
+    rule("entity.other.attribute-name", Base(0x09)),
+    rule(
+        "entity.other.attribute-name.id, punctuation.definition.entity",
+        Base(0x0D),
+    ),
+    rule("meta.selector", Base(0x0E)),
+    rule("none", Base(0x09)),
+    rule(
+        "markup.heading punctuation.definition.heading, entity.name.section",
+        Base(0x0D),
+    )
+    .font_style(FontStyle::empty()),
+    rule("keyword.other.unit", Base(0x09)),
+    rule("markup.bold, punctuation.definition.bold", Base(0x0A)).font_style(FontStyle::BOLD),
+    rule("markup.italic, punctuation.definition.italic", Base(0x0E)).font_style(FontStyle::ITALIC),
+    rule("markup.raw.inline", Base(0x0B)),
+    rule("string.other.link", Base(0x08)),
+    rule("meta.link", Base(0x09)),
+    rule("meta.image", Base(0x09)),
+    rule("markup.list", Base(0x08)),
+    rule("markup.quote", Base(0x09)),
+    rule("meta.separator", Base(0x05)).background(Base(0x02)),
+    rule("markup.inserted, markup.inserted.git_gutter", Base(0x0B)),
+    rule("markup.deleted, markup.deleted.git_gutter", Base(0x08)),
+    rule("markup.changed, markup.changed.git_gutter", Base(0x0E)),
+    rule("markup.ignored, markup.ignored.git_gutter", Base(0x02)),
+    rule("markup.untracked, markup.untracked.git_gutter", Base(0x02)),
+    rule("constant.other.color", Base(0x0C)),
+    rule("string.regexp", Base(0x0C)),
+    rule("constant.character.escape", Base(0x0C)),
+    rule(
+        "punctuation.section.embedded, variable.interpolation",
+        Base(0x0F),
+    ),
+    rule("invalid.illegal", Base(0x00)).background(Base(0x08)),
+    rule("markup.deleted.git_gutter", Fixed(rgb(0xf9, 0x26, 0x72))),
+    rule("markup.inserted.git_gutter", Fixed(rgb(0xa6, 0xe2, 0x2e))),
+    rule("markup.changed.git_gutter", Fixed(rgb(0x96, 0x7e, 0xfb))),
+    rule("markup.ignored.git_gutter", Fixed(rgb(0x56, 0x56, 0x56))),
+    rule("markup.untracked.git_gutter", Fixed(rgb(0x56, 0x56, 0x56))),
+];
+
+/// Builds a full syntax theme from a Base16 scheme
+///
+/// This uses the same template as the embedded `Base16*` themes e.g. the Eighties scheme gives
+/// the same styles as [`EmbeddedThemeName::Base16EightiesDark`]
+///
+/// [`EmbeddedThemeName::Base16EightiesDark`]: super::EmbeddedThemeName::Base16EightiesDark
+pub fn from_scheme(scheme: &Base16Scheme) -> Theme {
+    let paint = |paint| match paint {
+        Base(index) => scheme.palette[index],
+        Fixed(color) => color,
+    };
+    let base = |index: usize| Some(scheme.palette[index]);
+
+    let settings = ThemeSettings {
+        foreground: base(0x05),
+        background: base(0x00),
+        caret: base(0x05),
+        line_highlight: Some(Color {
+            a: 0x30,
+            ..scheme.palette[0x03]
+        }),
+        selection: base(0x02),
+        ..ThemeSettings::default()
+    };
+    let scopes = RULES
+        .iter()
+        .map(|rule| ThemeItem {
+            scope: ScopeSelectors::from_str(rule.scope).expect("Valid scope selector"),
+            style: StyleModifier {
+                foreground: Some(paint(rule.foreground)),
+                background: rule.background.map(paint),
+                font_style: rule.font_style,
+            },
+        })
+        .collect();
+
+    Theme {
+        name: Some(scheme.name.clone()),
+        author: scheme.author.clone(),
+        settings,
+        scopes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::theme::{extra, EmbeddedThemeName};
+
+    const EIGHTIES: &str = "\
+scheme: \"Eighties\"
+author: \"Chris Kempson (http://chriskempson.com)\"
+base00: \"2d2d2d\"
+base01: \"393939\"
+base02: \"515151\"
+base03: \"747369\"
+base04: \"a09f93\"
+base05: \"d3d0c8\"
+base06: \"e8e6df\"
+base07: \"f2f0ec\"
+base08: \"f2777a\"
+base09: \"f99157\"
+base0A: \"ffcc66\"
+base0B: \"99cc99\"
+base0C: \"66cccc\"
+base0D: \"6699cc\"
+base0E: \"cc99cc\"
+base0F: \"d27b53\"
+";
+
+    // The newer format with the colors nested under `palette`
+    const OCEAN: &str = "\
+# A comment
+system: \"base16\"
+name: 'Ocean'
+author: Chris Kempson (http://chriskempson.com) # Trailing comment
+variant: \"dark\"
+palette:
+  base00: \"#2b303b\"
+  base01: \"#343d46\"
+  base02: \"#4f5b66\"
+  base03: \"#65737e\"
+  base04: \"#a7adba\"
+  base05: \"#c0c5ce\"
+  base06: \"#dfe1e8\"
+  base07: \"#eff1f5\"
+  base08: \"#bf616a\"
+  base09: \"#d08770\"
+  base0A: \"#ebcb8b\"
+  base0B: \"#a3be8c\"
+  base0C: \"#96b5b4\"
+  base0D: \"#8fa1b3\"
+  base0E: \"#b48ead\"
+  base0F: \"#ab7967\"
+";
+
+    #[test]
+    fn matches_embedded_themes() {
+        let theme_set = extra();
+
+        let eighties = from_scheme(&EIGHTIES.parse().unwrap());
+        let embedded = theme_set.get(EmbeddedThemeName::Base16EightiesDark);
+        assert_eq!(eighties.author, embedded.author);
+        assert_eq!(eighties.settings, embedded.settings);
+        assert_eq!(eighties.scopes, embedded.scopes);
+
+        // Ocean's settings were tweaked upstream, but the scopes are the same
+        let ocean: Base16Scheme = OCEAN.parse().unwrap();
+        assert_eq!(ocean.name, "Ocean");
+        assert_eq!(
+            ocean.author.as_deref(),
+            Some("Chris Kempson (http://chriskempson.com)")
+        );
+        let ocean = from_scheme(&ocean);
+        let embedded = theme_set.get(EmbeddedThemeName::Base16OceanDark);
+        assert_eq!(ocean.scopes, embedded.scopes);
+    }
+
+    #[test]
+    fn base24() {
+        let mut scheme = OCEAN.to_owned();
+        for i in 0x10..0x18 {
+            scheme.push_str(&format!("  base{i:02X}: \"{i:02x}{i:02x}{i:02x}\"\n"));
+        }
+        let scheme: Base16Scheme = scheme.parse().unwrap();
+        let base24 = scheme.base24.unwrap();
+        assert_eq!(base24[0], rgb(0x10, 0x10, 0x10));
+        assert_eq!(base24[7], rgb(0x17, 0x17, 0x17));
+
+        let partial = format!("{OCEAN}  base10: \"101010\"\n");
+        let err = partial.parse::<Base16Scheme>().unwrap_err();
+        insta::assert_snapshot!(err, @"missing `base11`");
+    }
+
+    #[test]
+    fn invalid() {
+        let missing = EIGHTIES.replace("base0F: \"d27b53\"\n", "");
+        let err = missing.parse::<Base16Scheme>().unwrap_err();
+        insta::assert_snapshot!(err, @"missing `base0F`");
+
+        let invalid = EIGHTIES.replace("d27b53", "d27b5");
+        let err = invalid.parse::<Base16Scheme>().unwrap_err();
+        insta::assert_snapshot!(err, @"invalid color for `base0F`: `d27b5`");
+
+        let unnamed = EIGHTIES.replace("scheme:", "nonsense:");
+        let err = unnamed.parse::<Base16Scheme>().unwrap_err();
+        insta::assert_snapshot!(err, @"missing `scheme`");
+    }
+}
@@ -0,0 +1,287 @@
+use std::str::FromStr;
+
+use syntect::highlighting::{ScopeSelectors, StyleModifier, Theme, ThemeItem, ThemeSettings};
+
+use super::transform::{map_colors, settings_colors};
+use crate::color::{from_oklab, oklab};
+
+/// Customizes an existing theme
+///
+/// Scope rules get added after the existing ones (or replace a rule with the same selector), so
+/// they take priority over any equally specific rules. The color adjustments are applied to all of
+/// the colors that are in the theme at the time they're called, so call them before adding any
+/// rules that should be left untouched. Palette colors (see [`crate::terminal`]) are never
+/// adjusted
+///
+/// # Example
+///
+/// Nord, but with brighter comments and no italics in Markdown headings
+///
+/// ```
+/// use two_face::theme::{extra, EmbeddedThemeName, ThemeBuilder};
+/// use syntect::highlighting::{Color, FontStyle, StyleModifier};
+///
+/// let theme_set = extra();
+/// let theme = ThemeBuilder::new(theme_set.get(EmbeddedThemeName::Nord))
+///     .name("Nord Tweaked")
+///     .scope(
+///         "comment",
+///         StyleModifier {
+///             foreground: Some(Color { r: 0x81, g: 0x8c, b: 0xa3, a: 0xff }),
+///             ..StyleModifier::default()
+///         },
+///     )
+///     .scope(
+///         "markup.heading",
+///         StyleModifier {
+///             font_style: Some(FontStyle::BOLD),
+///             ..StyleModifier::default()
+///         },
+///     )
+///     .build();
+/// assert_eq!(theme.name.as_deref(), Some("Nord Tweaked"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ThemeBuilder {
+    theme: Theme,
+}
+
+impl ThemeBuilder {
+    /// Starts from a copy of `theme`
+    pub fn new(theme: &Theme) -> Self {
+        Self {
+            theme: theme.clone(),
+        }
+    }
+
+    /// Sets the theme's name
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.theme.name = Some(name.into());
+        self
+    }
+
+    /// Sets the theme's author
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.theme.author = Some(author.into());
+        self
+    }
+
+    /// Adds a rule, replacing any existing rule with the same scope selector
+    pub fn item(mut self, item: ThemeItem) -> Self {
+        let existing = self
+            .theme
+            .scopes
+            .iter_mut()
+            .find(|existing| existing.scope.selectors == item.scope.selectors);
+        match existing {
+            Some(existing) => existing.style = item.style,
+            None => self.theme.scopes.push(item),
+        }
+        self
+    }
+
+    /// Adds a rule for a scope selector like `"markup.heading, entity.name.section"`
+    ///
+    /// See [`ThemeBuilder::item()`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `selector` isn't a valid scope selector
+    pub fn scope(self, selector: &str, style: StyleModifier) -> Self {
+        let scope = ScopeSelectors::from_str(selector).expect("Invalid scope selector");
+        self.item(ThemeItem { scope, style })
+    }
+
+    /// Overrides the theme's settings with every field that's set in `overrides`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{extra, EmbeddedThemeName, ThemeBuilder};
+    /// use syntect::highlighting::{Color, ThemeSettings};
+    ///
+    /// let theme_set = extra();
+    /// let nord = theme_set.get(EmbeddedThemeName::Nord);
+    /// let black = Color { r: 0, g: 0, b: 0, a: 0xff };
+    /// let theme = ThemeBuilder::new(nord)
+    ///     .settings(ThemeSettings {
+    ///         background: Some(black),
+    ///         ..ThemeSettings::default()
+    ///     })
+    ///     .build();
+    /// assert_eq!(theme.settings.background, Some(black));
+    /// assert_eq!(theme.settings.foreground, nord.settings.foreground);
+    /// ```
+    pub fn settings(mut self, mut overrides: ThemeSettings) -> Self {
+        let settings = &mut self.theme.settings;
+        let colors = settings_colors(settings);
+        for (color, overridden) in colors.into_iter().zip(settings_colors(&mut overrides)) {
+            if overridden.is_some() {
+                *color = *overridden;
+            }
+        }
+
+        let ThemeSettings {
+            popup_css,
+            phantom_css,
+            bracket_contents_options,
+            brackets_options,
+            tags_options,
+            ..
+        } = overrides;
+        if popup_css.is_some() {
+            settings.popup_css = popup_css;
+        }
+        if phantom_css.is_some() {
+            settings.phantom_css = phantom_css;
+        }
+        let options = [
+            (
+                &mut settings.bracket_contents_options,
+                bracket_contents_options,
+            ),
+            (&mut settings.brackets_options, brackets_options),
+            (&mut settings.tags_options, tags_options),
+        ];
+        for (option, overridden) in options {
+            if overridden.is_some() {
+                *option = overridden;
+            }
+        }
+        self
+    }
+
+    /// Scales the colorfulness (chroma) of every color where `0.0` gives grayscale
+    pub fn saturation(self, factor: f32) -> Self {
+        self.map_oklch(|[l, c, h]| [l, c * factor, h])
+    }
+
+    /// Scales the perceived lightness of every color
+    pub fn brightness(self, factor: f32) -> Self {
+        self.map_oklch(|[l, c, h]| [(l * factor).clamp(0.0, 1.0), c, h])
+    }
+
+    /// Rotates the hue of every color by `degrees`
+    pub fn hue_shift(self, degrees: f32) -> Self {
+        self.map_oklch(|[l, c, h]| [l, c, h + degrees.to_radians()])
+    }
+
+    /// Finishes building the theme
+    pub fn build(self) -> Theme {
+        self.theme
+    }
+
+    /// Applies `f` to every RGB color in its OKLCh form (with the hue in radians)
+    fn map_oklch(mut self, f: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        self.theme = map_colors(&self.theme, |color| {
+            // Leave palette indices and the terminal's default color alone
+            if color.a <= 1 {
+                return color;
+            }
+            let [l, a, b] = oklab(color);
+            let [l, c, h] = f([l, a.hypot(b), b.atan2(a)]);
+            from_oklab([l, c * h.cos(), c * h.sin()], color.a)
+        });
+        self
+    }
+}
+
+impl From<Theme> for ThemeBuilder {
+    fn from(theme: Theme) -> Self {
+        Self { theme }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use syntect::highlighting::{Color, FontStyle};
+
+    use crate::{
+        color::rgb,
+        theme::{extra, EmbeddedLazyThemeSet, EmbeddedThemeName},
+    };
+
+    #[test]
+    fn identity_adjustments() {
+        let theme_set = extra();
+        for &name in EmbeddedLazyThemeSet::theme_names() {
+            let theme = theme_set.get(name);
+            let adjusted = ThemeBuilder::new(theme)
+                .saturation(1.0)
+                .brightness(1.0)
+                .hue_shift(360.0)
+                .build();
+            assert_eq!(&adjusted, theme, "{name}");
+        }
+    }
+
+    #[test]
+    fn adjustments() {
+        let theme = Theme {
+            settings: ThemeSettings {
+                foreground: Some(rgb(0xd0, 0x40, 0x40)),
+                // Palette colors are left as is
+                background: Some(Color {
+                    r: 4,
+                    g: 0,
+                    b: 0,
+                    a: 0,
+                }),
+                ..ThemeSettings::default()
+            },
+            ..Theme::default()
+        };
+        let adjust = |f: fn(ThemeBuilder) -> ThemeBuilder| {
+            let settings = f(ThemeBuilder::new(&theme)).build().settings;
+            (settings.foreground.unwrap(), settings.background)
+        };
+
+        let (gray, bg) = adjust(|b| b.saturation(0.0));
+        assert!(gray.r == gray.g && gray.g == gray.b);
+        assert_eq!(bg, theme.settings.background);
+        let (darker, _) = adjust(|b| b.brightness(0.5));
+        assert!(darker.r < 0xd0 && darker.g < 0x40);
+        let (shifted, _) = adjust(|b| b.hue_shift(120.0));
+        assert!(shifted.g > shifted.r && shifted.g > shifted.b);
+    }
+
+    #[test]
+    fn items_and_settings() {
+        let theme_set = extra();
+        let nord = theme_set.get(EmbeddedThemeName::Nord);
+        let white = rgb(0xff, 0xff, 0xff);
+        let replaced = StyleModifier {
+            foreground: Some(white),
+            ..StyleModifier::default()
+        };
+        let heading = StyleModifier {
+            font_style: Some(FontStyle::empty()),
+            ..StyleModifier::default()
+        };
+        let first = ThemeItem {
+            scope: nord.scopes[0].scope.clone(),
+            style: replaced,
+        };
+        let theme = ThemeBuilder::new(nord)
+            .item(first)
+            .scope("markup.heading.markdown.two-face", heading)
+            .settings(ThemeSettings {
+                caret: Some(white),
+                popup_css: Some("html {}".into()),
+                ..ThemeSettings::default()
+            })
+            .build();
+
+        // Rules with an existing selector get replaced in place while new ones get appended
+        assert_eq!(theme.scopes.len(), nord.scopes.len() + 1);
+        assert_eq!(theme.scopes[0].style, replaced);
+        assert_eq!(theme.scopes[1..nord.scopes.len()], nord.scopes[1..]);
+        assert_eq!(theme.scopes.last().unwrap().style, heading);
+
+        assert_eq!(theme.settings.caret, Some(white));
+        assert_eq!(theme.settings.popup_css.as_deref(), Some("html {}"));
+        assert_eq!(theme.settings.background, nord.settings.background);
+    }
+}
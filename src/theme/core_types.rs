@@ -1,9 +1,21 @@
-use std::{collections::BTreeMap, sync::OnceLock};
+use std::{
+    borrow::Cow,
+    collections::{btree_map, BTreeMap},
+    fmt,
+    iter::FusedIterator,
+    mem,
+    num::NonZeroUsize,
+    ops::Index,
+    sync::OnceLock,
+    thread,
+};
 
+use serde::ser::Serializer;
 use serde_derive::{Deserialize, Serialize};
 use syntect::{
     dumps::{dump_binary, from_binary},
-    highlighting::{Theme, ThemeSet},
+    highlighting::{ScopeSelector, Theme, ThemeItem, ThemeSet},
+    parsing::{Scope, ScopeStack},
 };
 
 /// A [`ThemeSet`] that lazily deserializes/decompresses a single theme at a time
@@ -23,10 +35,11 @@ use syntect::{
 /// let theme_set: LazyThemeSet = LazyThemeSet::from(extra());
 /// let syntect_theme_set = syntect::highlighting::ThemeSet::from(&theme_set);
 /// ```
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LazyThemeSet {
     // Can't be public since people can tweak `LazyTheme`'s internal data to get deserialization to
     // fail
+    #[serde(serialize_with = "serialize_themes")]
     pub(crate) themes: BTreeMap<String, LazyTheme>,
 }
 
@@ -34,7 +47,8 @@ impl LazyThemeSet {
     /// Access a single theme from the set
     ///
     /// Calling this multiple times for the same theme will only deserialize and decompress the
-    /// theme once
+    /// theme once. Returns `None` when the set doesn't include the theme or when a theme that was
+    /// added without being parsed turns out to be invalid
     ///
     /// # Example
     ///
@@ -48,11 +62,155 @@ impl LazyThemeSet {
     /// let nord2 = theme_set.get("Nord").unwrap();
     /// ```
     pub fn get(&self, name: &str) -> Option<&Theme> {
-        self.themes.get(name).map(|lazy_theme| {
-            lazy_theme
-                .deserialized
-                .get_or_init(|| lazy_theme.deserialize())
-        })
+        self.themes.get(name).and_then(LazyTheme::get)
+    }
+
+    /// Adds a theme to the set, replacing any existing theme with the same name
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{extra, EmbeddedThemeName, LazyThemeSet, ThemeBuilder};
+    ///
+    /// let embedded = extra();
+    /// let tweaked = ThemeBuilder::new(embedded.get(EmbeddedThemeName::Nord))
+    ///     .saturation(0.5)
+    ///     .build();
+    /// let mut theme_set = LazyThemeSet::from(embedded);
+    /// theme_set.insert("Nord Muted", tweaked);
+    /// assert!(theme_set.contains("Nord Muted"));
+    /// ```
+    pub fn insert(&mut self, name: impl Into<String>, theme: Theme) {
+        self.themes.insert(name.into(), LazyTheme::loaded(theme));
+    }
+
+    /// Removes a theme from the set, returning it if it was included
+    pub fn remove(&mut self, name: &str) -> Option<Theme> {
+        self.themes.remove(name).and_then(LazyTheme::into_theme)
+    }
+
+    /// Moves all of the themes from `other` into this set without loading them
+    ///
+    /// Themes from `other` replace any themes with the same name
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{extra, LazyThemeSet};
+    /// use syntect::highlighting::{Theme, ThemeSet};
+    ///
+    /// let mut theme_set = LazyThemeSet::from(&ThemeSet::new());
+    /// theme_set.insert("Mine", Theme::default());
+    /// theme_set.extend(extra());
+    /// assert!(theme_set.contains("Mine"));
+    /// assert!(theme_set.contains("Nord"));
+    /// ```
+    pub fn extend(&mut self, other: impl Into<Self>) {
+        self.themes.append(&mut other.into().themes);
+    }
+
+    /// The number of themes in the set
+    pub fn len(&self) -> usize {
+        self.themes.len()
+    }
+
+    /// Whether the set has no themes at all
+    pub fn is_empty(&self) -> bool {
+        self.themes.is_empty()
+    }
+
+    /// Whether the set includes a theme with the given name
+    ///
+    /// This doesn't load the theme
+    pub fn contains(&self, name: &str) -> bool {
+        self.themes.contains_key(name)
+    }
+
+    /// Loads every theme in the set, spreading the work across threads
+    ///
+    /// Useful for warming things up front when most of the themes will be used, e.g. for a theme
+    /// gallery
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{extra, LazyThemeSet};
+    ///
+    /// let theme_set = LazyThemeSet::from(extra());
+    /// theme_set.preload_all();
+    /// assert!(theme_set.memory_usage("Nord").unwrap().loaded.is_some());
+    /// ```
+    pub fn preload_all(&self) {
+        let unloaded: Vec<_> = self
+            .themes
+            .values()
+            .filter(|lazy| lazy.deserialized.get().is_none())
+            .collect();
+        if unloaded.is_empty() {
+            return;
+        }
+        let num_threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk_size = unloaded.len().div_ceil(num_threads);
+        thread::scope(|s| {
+            for chunk in unloaded.chunks(chunk_size) {
+                s.spawn(move || {
+                    for lazy in chunk {
+                        let _ = lazy.get();
+                    }
+                });
+            }
+        });
+    }
+
+    /// Frees a theme's loaded form, returning whether it was loaded
+    ///
+    /// The theme gets loaded again the next time it's accessed. Themes that were added through
+    /// [`LazyThemeSet::insert()`] get serialized first, so they stay in the set
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{extra, LazyThemeSet};
+    ///
+    /// let mut theme_set = LazyThemeSet::from(extra());
+    /// let _ = theme_set.get("Nord");
+    /// assert!(theme_set.evict("Nord"));
+    /// assert!(!theme_set.evict("Nord"));
+    /// assert!(theme_set.get("Nord").is_some());
+    /// ```
+    pub fn evict(&mut self, name: &str) -> bool {
+        self.themes.get_mut(name).is_some_and(LazyTheme::evict)
+    }
+
+    /// Frees the loaded form of every theme
+    ///
+    /// See [`LazyThemeSet::evict()`]
+    pub fn evict_all(&mut self) {
+        for lazy in self.themes.values_mut() {
+            lazy.evict();
+        }
+    }
+
+    /// Roughly how much memory a theme takes up
+    ///
+    /// Returns `None` when the set doesn't include the theme
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{extra, LazyThemeSet};
+    ///
+    /// let theme_set = LazyThemeSet::from(extra());
+    /// let usage = theme_set.memory_usage("Nord").unwrap();
+    /// assert!(usage.serialized > 0);
+    /// assert_eq!(usage.loaded, None);
+    ///
+    /// let _ = theme_set.get("Nord");
+    /// let usage = theme_set.memory_usage("Nord").unwrap();
+    /// assert!(usage.loaded.unwrap() > usage.serialized);
+    /// ```
+    pub fn memory_usage(&self, name: &str) -> Option<MemoryUsage> {
+        self.themes.get(name).map(LazyTheme::memory_usage)
     }
 
     /// Iterate over all the theme names included in the set
@@ -66,12 +224,165 @@ impl LazyThemeSet {
     /// // Nord should be included
     /// assert!(theme_set.theme_names().find(|&name| name == "Nord").is_some());
     /// ```
-    // TODO: use a nameable iterator here
-    pub fn theme_names(&self) -> impl Iterator<Item = &str> {
-        self.themes.keys().map(|name| name.as_str())
+    pub fn theme_names(&self) -> Names<'_> {
+        Names(self.themes.keys())
+    }
+
+    /// Iterate over all the themes in the set, loading each theme as it's reached
+    ///
+    /// Themes that fail to load are skipped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{extra, LazyThemeSet};
+    ///
+    /// let theme_set = LazyThemeSet::from(extra());
+    /// // Only loads the themes up to Nord
+    /// let (_, nord) = theme_set.iter().find(|&(name, _)| name == "Nord").unwrap();
+    /// ```
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.themes.iter())
     }
 }
 
+/// An estimate of the memory used by a theme in a [`LazyThemeSet`]
+///
+/// Returned by [`LazyThemeSet::memory_usage()`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Bytes used by the theme's serialized form
+    pub serialized: usize,
+    /// Bytes used by the loaded theme or `None` if it hasn't been loaded
+    pub loaded: Option<usize>,
+}
+
+impl MemoryUsage {
+    /// Bytes used in total
+    pub fn total(&self) -> usize {
+        self.serialized + self.loaded.unwrap_or(0)
+    }
+}
+
+impl Index<&str> for LazyThemeSet {
+    type Output = Theme;
+
+    /// # Panics
+    ///
+    /// Panics if the theme isn't included or fails to load
+    fn index(&self, name: &str) -> &Self::Output {
+        self.get(name).expect("Theme not found")
+    }
+}
+
+impl<'a> IntoIterator for &'a LazyThemeSet {
+    type Item = (&'a str, &'a Theme);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for LazyThemeSet {
+    type Item = (String, Theme);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.themes.into_iter())
+    }
+}
+
+/// An iterator over the theme names in a [`LazyThemeSet`]
+///
+/// Returned by [`LazyThemeSet::theme_names()`]
+#[derive(Clone, Debug)]
+pub struct Names<'a>(btree_map::Keys<'a, String, LazyTheme>);
+
+impl<'a> Iterator for Names<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(String::as_str)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Names<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(String::as_str)
+    }
+}
+
+impl ExactSizeIterator for Names<'_> {}
+
+impl FusedIterator for Names<'_> {}
+
+/// A lazily loading iterator over the themes in a [`LazyThemeSet`]
+///
+/// Returned by [`LazyThemeSet::iter()`]
+#[derive(Clone, Debug)]
+pub struct Iter<'a>(btree_map::Iter<'a, String, LazyTheme>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a Theme);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .by_ref()
+            .find_map(|(name, lazy)| Some((name.as_str(), lazy.get()?)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.0.size_hint().1)
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0
+            .by_ref()
+            .rev()
+            .find_map(|(name, lazy)| Some((name.as_str(), lazy.get()?)))
+    }
+}
+
+impl FusedIterator for Iter<'_> {}
+
+/// An owning iterator over the themes in a [`LazyThemeSet`]
+///
+/// Themes that fail to load are skipped
+#[derive(Debug)]
+pub struct IntoIter(btree_map::IntoIter<String, LazyTheme>);
+
+impl Iterator for IntoIter {
+    type Item = (String, Theme);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .by_ref()
+            .find_map(|(name, lazy)| Some((name, lazy.into_theme()?)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.0.size_hint().1)
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0
+            .by_ref()
+            .rev()
+            .find_map(|(name, lazy)| Some((name, lazy.into_theme()?)))
+    }
+}
+
+impl FusedIterator for IntoIter {}
+
 impl From<&ThemeSet> for LazyThemeSet {
     fn from(full_themes: &ThemeSet) -> Self {
         let themes = full_themes
@@ -88,7 +399,7 @@ impl From<&LazyThemeSet> for ThemeSet {
         let themes = lazy_themes
             .themes
             .iter()
-            .map(|(name, lazy)| (name.to_owned(), lazy.into()))
+            .filter_map(|(name, lazy)| Some((name.to_owned(), lazy.load()?)))
             .collect();
         Self { themes }
     }
@@ -100,31 +411,161 @@ impl From<LazyThemeSet> for ThemeSet {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// Parses a theme from its serialized form, returning `None` if it's invalid
+pub(crate) type ParseTheme = fn(&[u8]) -> Option<Theme>;
+
+#[derive(Clone, Deserialize)]
 pub(crate) struct LazyTheme {
-    serialized: Vec<u8>,
+    pub(crate) serialized: Vec<u8>,
 
+    /// How to parse `serialized` when it's not a [`syntect::dumps`] binary dump
     #[serde(skip, default)]
-    deserialized: OnceLock<Theme>,
+    pub(crate) parse: Option<ParseTheme>,
+
+    #[serde(skip, default)]
+    pub(crate) deserialized: OnceLock<Option<Theme>>,
 }
 
 impl LazyTheme {
-    fn deserialize(&self) -> Theme {
-        from_binary(&self.serialized)
+    fn loaded(theme: Theme) -> Self {
+        Self {
+            serialized: Vec::new(),
+            parse: None,
+            deserialized: OnceLock::from(Some(theme)),
+        }
+    }
+
+    fn get(&self) -> Option<&Theme> {
+        self.deserialized
+            .get_or_init(|| self.deserialize())
+            .as_ref()
+    }
+
+    /// Like [`LazyTheme::get()`], but without holding on to the loaded theme
+    fn load(&self) -> Option<Theme> {
+        match self.deserialized.get() {
+            Some(theme) => theme.clone(),
+            None => self.deserialize(),
+        }
+    }
+
+    /// The theme as a [`syntect::dumps`] binary dump, returning `None` if it's invalid
+    fn dump(&self) -> Option<Cow<'_, [u8]>> {
+        if self.parse.is_none() && !self.serialized.is_empty() {
+            Some(Cow::Borrowed(&self.serialized))
+        } else {
+            self.load().map(|theme| Cow::Owned(dump_binary(&theme)))
+        }
+    }
+
+    fn into_theme(mut self) -> Option<Theme> {
+        match self.deserialized.take() {
+            Some(theme) => theme,
+            None => self.deserialize(),
+        }
     }
+
+    fn evict(&mut self) -> bool {
+        if self.serialized.is_empty() && self.parse.is_none() {
+            // Added as an already loaded theme, so it needs a serialized form to fall back to
+            if let Some(Some(theme)) = self.deserialized.get() {
+                self.serialized = dump_binary(theme);
+            }
+        }
+        self.deserialized.take().is_some()
+    }
+
+    fn memory_usage(&self) -> MemoryUsage {
+        let loaded = self.deserialized.get().map(|theme| {
+            mem::size_of::<Option<Theme>>() + theme.as_ref().map_or(0, theme_heap_size)
+        });
+        MemoryUsage {
+            serialized: self.serialized.capacity(),
+            loaded,
+        }
+    }
+
+    fn deserialize(&self) -> Option<Theme> {
+        match self.parse {
+            Some(parse) => parse(&self.serialized),
+            None => Some(from_binary(&self.serialized)),
+        }
+    }
+}
+
+/// The heap allocations owned by a theme, ignoring any allocator overhead
+fn theme_heap_size(theme: &Theme) -> usize {
+    let strings = [
+        &theme.name,
+        &theme.author,
+        &theme.settings.popup_css,
+        &theme.settings.phantom_css,
+    ];
+    let strings_size: usize = strings.into_iter().flatten().map(String::capacity).sum();
+    let stack_size = |stack: &ScopeStack| stack.scopes.capacity() * mem::size_of::<Scope>();
+    let scopes_size: usize = theme
+        .scopes
+        .iter()
+        .map(|item| {
+            let selectors = &item.scope.selectors;
+            let selectors_size: usize = selectors
+                .iter()
+                .map(|selector| {
+                    let excludes = &selector.excludes;
+                    stack_size(&selector.path)
+                        + excludes.capacity() * mem::size_of::<ScopeStack>()
+                        + excludes.iter().map(stack_size).sum::<usize>()
+                })
+                .sum();
+            selectors.capacity() * mem::size_of::<ScopeSelector>() + selectors_size
+        })
+        .sum();
+    let items_size = theme.scopes.capacity() * mem::size_of::<ThemeItem>();
+    strings_size + items_size + scopes_size
 }
 
-impl From<&LazyTheme> for Theme {
-    fn from(lazy: &LazyTheme) -> Self {
-        lazy.deserialize()
+impl fmt::Debug for LazyTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyTheme")
+            .field("serialized_len", &self.serialized.len())
+            .field("loaded", &self.deserialized.get().is_some())
+            .finish_non_exhaustive()
     }
 }
 
+// Themes always get serialized as binary dumps since the parser can't be serialized
+/// The serialized form of a [`LazyTheme`]
+#[derive(Serialize)]
+#[serde(rename = "LazyTheme")]
+struct DumpedTheme<'a> {
+    serialized: Cow<'a, [u8]>,
+}
+
+/// Leaves out any themes that fail to load like the conversion to a [`ThemeSet`] does
+fn serialize_themes<S: Serializer>(
+    themes: &BTreeMap<String, LazyTheme>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let dumped: Vec<_> = themes
+        .iter()
+        .filter_map(|(name, theme)| {
+            Some((
+                name,
+                DumpedTheme {
+                    serialized: theme.dump()?,
+                },
+            ))
+        })
+        .collect();
+    serializer.collect_map(dumped)
+}
+
 impl From<&Theme> for LazyTheme {
     fn from(theme: &Theme) -> Self {
         let serialized = dump_binary(theme);
         Self {
             serialized,
+            parse: None,
             deserialized: OnceLock::new(),
         }
     }
@@ -0,0 +1,564 @@
+use std::fmt::Write;
+
+use syntect::{
+    highlighting::{Color, FontStyle, Theme, ThemeItem},
+    html::ClassStyle,
+    parsing::Scope,
+};
+
+use super::{extra, EmbeddedLazyThemeSet, EmbeddedThemeName};
+use crate::terminal::AnsiColor;
+
+/// Generates a stylesheet for one of the embedded themes
+///
+/// The classes match the output of [`syntect::html::ClassedHTMLGenerator`] when using the same
+/// `style`, and the code block's container is expected to have the `code` class (with the prefix
+/// when using [`ClassStyle::SpacedPrefixed`]). See [`css_for_theme()`] for the details
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::{css, EmbeddedThemeName};
+/// use syntect::html::ClassStyle;
+///
+/// let stylesheet = css(EmbeddedThemeName::Nord, ClassStyle::Spaced);
+/// assert!(stylesheet.starts_with("/* Nord */\n.code {\n"));
+/// ```
+pub fn css(name: EmbeddedThemeName, style: ClassStyle) -> String {
+    css_for_theme(extra().get(name), style)
+}
+
+/// Generates a single stylesheet covering all of the embedded themes
+///
+/// Each theme's rules are scoped under a `[data-theme="..."]` selector using
+/// [`EmbeddedThemeName::as_slug()`], so switching themes is just a matter of changing the
+/// attribute on any ancestor of the code blocks
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::css_all_themes;
+/// use syntect::html::ClassStyle;
+///
+/// let stylesheet = css_all_themes(ClassStyle::Spaced);
+/// assert!(stylesheet.contains("[data-theme=\"nord\"] .code {\n"));
+/// ```
+pub fn css_all_themes(style: ClassStyle) -> String {
+    let theme_set = extra();
+    let mut css = String::new();
+    for (i, &name) in EmbeddedLazyThemeSet::theme_names().iter().enumerate() {
+        if i != 0 {
+            css.push('\n');
+        }
+        let scope = format!("[data-theme=\"{}\"]", name.as_slug());
+        write_theme_css(&mut css, theme_set.get(name), style, Some(&scope));
+    }
+    css
+}
+
+/// Generates a stylesheet for any theme
+///
+/// Unlike [`syntect::html::css_for_theme_with_class_style()`] this understands the
+/// palette-encoded colors used by the terminal themes (see [`crate::terminal`]). Palette colors
+/// reference a `--ansi-<index>` custom property that falls back to xterm's default color, and the
+/// terminal's default color leaves the property unset. Exclusions in scope selectors (e.g.
+/// `string - string.quoted`) can't be represented, so they're ignored
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::{css_for_theme, extra, EmbeddedThemeName};
+/// use syntect::html::ClassStyle;
+///
+/// let theme_set = extra();
+/// let stylesheet = css_for_theme(
+///     &theme_set[EmbeddedThemeName::Base16],
+///     ClassStyle::SpacedPrefixed { prefix: "hl-" },
+/// );
+/// assert!(stylesheet.contains(".hl-comment {\n  color: var(--ansi-8, #7f7f7f);\n}\n"));
+/// ```
+pub fn css_for_theme(theme: &Theme, style: ClassStyle) -> String {
+    let mut css = String::new();
+    write_theme_css(&mut css, theme, style, None);
+    css
+}
+
+fn write_theme_css(css: &mut String, theme: &Theme, style: ClassStyle, scope: Option<&str>) {
+    let name = theme.name.as_deref().unwrap_or("unknown theme");
+    writeln!(css, "/* {} */", name.replace("*/", "* /")).expect("Infallible");
+
+    let scope_prefix = scope.map(|scope| format!("{scope} ")).unwrap_or_default();
+    let code = format!("{scope_prefix}{}", class_selector("code", style));
+    writeln!(css, "{code} {{").expect("Infallible");
+    write_color_property(css, "color", theme.settings.foreground);
+    write_color_property(css, "background-color", theme.settings.background);
+    css.push_str("}\n");
+    // The extra elements from `crate::html::Renderer`
+    let line_number = class_selector("code-line-number", style);
+    writeln!(css, "{code} {line_number} {{").expect("Infallible");
+    write_color_property(css, "color", theme.settings.gutter_foreground);
+    write_color_property(css, "background-color", theme.settings.gutter);
+    css.push_str("  user-select: none;\n}\n");
+    if let Some(line_highlight) = theme.settings.line_highlight {
+        let highlighted = class_selector("code-line-highlighted", style);
+        writeln!(css, "{code} {highlighted} {{").expect("Infallible");
+        write_color_property(css, "background-color", Some(line_highlight));
+        css.push_str("  display: block;\n}\n");
+    }
+
+    for item in &theme.scopes {
+        let Some(selectors) = item_selectors(item, style, &scope_prefix) else {
+            continue;
+        };
+
+        writeln!(css, "{selectors} {{").expect("Infallible");
+        write_color_property(css, "color", item.style.foreground);
+        write_color_property(css, "background-color", item.style.background);
+        let font_style = item.style.font_style.unwrap_or_else(FontStyle::empty);
+        for (flag, property) in [
+            (FontStyle::BOLD, "font-weight: bold"),
+            (FontStyle::ITALIC, "font-style: italic"),
+            (FontStyle::UNDERLINE, "text-decoration: underline"),
+        ] {
+            if font_style.contains(flag) {
+                writeln!(css, "  {property};").expect("Infallible");
+            }
+        }
+        css.push_str("}\n");
+    }
+}
+
+/// Generates a stylesheet that switches between a light and a dark embedded theme
+///
+/// See [`dual_css_for_themes()`] for the details
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::{dual_css, EmbeddedThemeName};
+/// use syntect::html::ClassStyle;
+///
+/// let stylesheet = dual_css(EmbeddedThemeName::Github, EmbeddedThemeName::Nord, ClassStyle::Spaced);
+/// assert!(stylesheet.contains("@media (prefers-color-scheme: dark)"));
+/// ```
+pub fn dual_css(light: EmbeddedThemeName, dark: EmbeddedThemeName, style: ClassStyle) -> String {
+    let theme_set = extra();
+    dual_css_for_themes(theme_set.get(light), theme_set.get(dark), style)
+}
+
+/// Generates a stylesheet that switches between a light and a dark theme
+///
+/// This works with the same class-based HTML as [`css_for_theme()`], so the code only has to be
+/// highlighted once. Each theme's rules set their own set of custom properties (e.g.
+/// `--code-light-fg` and `--code-dark-fg`), and which set gets used is picked through the
+/// `prefers-color-scheme` media query. Setting a `data-color-scheme="light"` or
+/// `data-color-scheme="dark"` attribute on any ancestor of the code blocks overrides the media
+/// query, which makes for an easy toggle
+///
+/// Since the themes don't share any properties, a scope that only one theme styles can't leak
+/// its style into the other
+pub fn dual_css_for_themes(light: &Theme, dark: &Theme, style: ClassStyle) -> String {
+    let code = class_selector("code", style);
+    let var_prefix = format!("--{}", escape_css_identifier(&class_name("code", style)));
+    let line_number = class_selector("code-line-number", style);
+    let highlighted = class_selector("code-line-highlighted", style);
+    let mut css = String::new();
+    for (theme, scheme) in [(light, "light"), (dark, "dark")] {
+        let prefix = format!("{var_prefix}-{scheme}");
+        let name = theme.name.as_deref().unwrap_or("unknown theme");
+        writeln!(css, "/* {scheme}: {} */", name.replace("*/", "* /")).expect("Infallible");
+        writeln!(css, "{code} {{").expect("Infallible");
+        write_color_property(&mut css, &format!("{prefix}-fg"), theme.settings.foreground);
+        write_color_property(&mut css, &format!("{prefix}-bg"), theme.settings.background);
+        let settings = &theme.settings;
+        write_color_property(&mut css, &format!("{prefix}-gutter"), settings.gutter);
+        write_color_property(
+            &mut css,
+            &format!("{prefix}-gutter-fg"),
+            settings.gutter_foreground,
+        );
+        write_color_property(
+            &mut css,
+            &format!("{prefix}-line-highlight"),
+            settings.line_highlight,
+        );
+        css.push_str("}\n");
+
+        for item in &theme.scopes {
+            let Some(selectors) = item_selectors(item, style, "") else {
+                continue;
+            };
+            writeln!(css, "{selectors} {{").expect("Infallible");
+            write_color_property(&mut css, &format!("{prefix}-fg"), item.style.foreground);
+            write_color_property(
+                &mut css,
+                &format!("{prefix}-scope-bg"),
+                item.style.background,
+            );
+            // Explicitly reset the font styles since the properties are inherited
+            if let Some(font_style) = item.style.font_style {
+                for (flag, property, set, unset) in [
+                    (FontStyle::BOLD, "font-weight", "bold", "normal"),
+                    (FontStyle::ITALIC, "font-style", "italic", "normal"),
+                    (FontStyle::UNDERLINE, "text-decoration", "underline", "none"),
+                ] {
+                    let value = if font_style.contains(flag) {
+                        set
+                    } else {
+                        unset
+                    };
+                    writeln!(css, "  {prefix}-{property}: {value};").expect("Infallible");
+                }
+            }
+            css.push_str("}\n");
+        }
+        css.push('\n');
+    }
+
+    let write_bindings = |css: &mut String, selector_prefix: &str, scheme: &str| {
+        let prefix = format!("{var_prefix}-{scheme}");
+        let container = format!("{selector_prefix}{code}");
+        writeln!(css, "{container} {{").expect("Infallible");
+        writeln!(css, "  color: var({prefix}-fg);").expect("Infallible");
+        writeln!(css, "  background-color: var({prefix}-bg);").expect("Infallible");
+        writeln!(css, "}}").expect("Infallible");
+        writeln!(css, "{container} span {{").expect("Infallible");
+        writeln!(css, "  color: var({prefix}-fg);").expect("Infallible");
+        writeln!(
+            css,
+            "  background-color: var({prefix}-scope-bg, transparent);"
+        )
+        .expect("Infallible");
+        for property in ["font-weight", "font-style", "text-decoration"] {
+            writeln!(css, "  {property}: var({prefix}-{property}, inherit);").expect("Infallible");
+        }
+        writeln!(css, "}}").expect("Infallible");
+        writeln!(css, "{container} {line_number} {{").expect("Infallible");
+        writeln!(css, "  color: var({prefix}-gutter-fg, inherit);").expect("Infallible");
+        writeln!(
+            css,
+            "  background-color: var({prefix}-gutter, transparent);"
+        )
+        .expect("Infallible");
+        writeln!(css, "  user-select: none;").expect("Infallible");
+        writeln!(css, "}}").expect("Infallible");
+        writeln!(css, "{container} {highlighted} {{").expect("Infallible");
+        writeln!(css, "  display: block;").expect("Infallible");
+        writeln!(
+            css,
+            "  background-color: var({prefix}-line-highlight, transparent);"
+        )
+        .expect("Infallible");
+        writeln!(css, "}}").expect("Infallible");
+    };
+
+    css.push_str("/* Light by default */\n");
+    write_bindings(&mut css, "", "light");
+    css.push_str("@media (prefers-color-scheme: dark) {\n");
+    let mut media = String::new();
+    write_bindings(&mut media, "", "dark");
+    for line in media.lines() {
+        writeln!(css, "  {line}").expect("Infallible");
+    }
+    css.push_str("}\n");
+    css.push_str("/* Explicit overrides */\n");
+    for scheme in ["light", "dark"] {
+        write_bindings(
+            &mut css,
+            &format!("[data-color-scheme=\"{scheme}\"] "),
+            scheme,
+        );
+    }
+    css
+}
+
+/// The combined selectors for a theme item or [`None`] if there's nothing to select
+fn item_selectors(item: &ThemeItem, style: ClassStyle, scope_prefix: &str) -> Option<String> {
+    let selectors: Vec<_> = item
+        .scope
+        .selectors
+        .iter()
+        .map(|selector| {
+            let path: Vec<_> = selector
+                .extract_scopes()
+                .into_iter()
+                .map(|scope| scope_selector(scope, style))
+                .collect();
+            path.join(" ")
+        })
+        .filter(|selector| !selector.is_empty())
+        .map(|selector| format!("{scope_prefix}{selector}"))
+        .collect();
+    (!selectors.is_empty()).then(|| selectors.join(", "))
+}
+
+fn write_color_property(css: &mut String, property: &str, color: Option<Color>) {
+    if let Some(value) = color.and_then(css_color) {
+        writeln!(css, "  {property}: {value};").expect("Infallible");
+    }
+}
+
+/// The CSS value for a theme color
+///
+/// Returns [`None`] for the terminal's default color since that should be left up to the page
+pub(crate) fn css_color(color: Color) -> Option<String> {
+    match AnsiColor::from(color) {
+        AnsiColor::Default => None,
+        AnsiColor::Palette(index) => {
+            let fallback = crate::color::xterm_rgb(index);
+            Some(format!(
+                "var(--ansi-{index}, #{:02x}{:02x}{:02x})",
+                fallback.r, fallback.g, fallback.b
+            ))
+        }
+        AnsiColor::Rgb { r, g, b } if color.a == 0xff => Some(format!("#{r:02x}{g:02x}{b:02x}")),
+        AnsiColor::Rgb { r, g, b } => Some(format!("#{r:02x}{g:02x}{b:02x}{:02x}", color.a)),
+    }
+}
+
+/// A compound class selector matching every atom of the scope (e.g. `.string.quoted`)
+fn scope_selector(scope: Scope, style: ClassStyle) -> String {
+    scope
+        .build_string()
+        .split('.')
+        .map(|atom| class_selector(atom, style))
+        .collect()
+}
+
+/// The class that the HTML uses for `atom`, which matches what `syntect` emits for scope atoms
+pub(crate) fn class_name(atom: &str, style: ClassStyle) -> String {
+    match style {
+        ClassStyle::SpacedPrefixed { prefix } => format!("{prefix}{atom}"),
+        _ => atom.to_owned(),
+    }
+}
+
+/// A class selector for [`class_name()`] (e.g. `.hl-code`)
+fn class_selector(atom: &str, style: ClassStyle) -> String {
+    format!(".{}", escape_css_identifier(&class_name(atom, style)))
+}
+
+/// Escapes any characters that aren't allowed unescaped within a CSS identifier
+fn escape_css_identifier(identifier: &str) -> String {
+    let mut escaped = String::with_capacity(identifier.len());
+    for (i, c) in identifier.char_indices() {
+        if c.is_ascii_alphabetic() || c == '-' || c == '_' || (i > 0 && c.is_ascii_digit()) {
+            escaped.push(c);
+        } else {
+            write!(escaped, "\\{:x} ", u32::from(c)).expect("Infallible");
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use syntect::highlighting::{ScopeSelectors, StyleModifier, ThemeSettings};
+
+    fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 0xff }
+    }
+
+    fn tiny_theme() -> Theme {
+        Theme {
+            name: Some("Tiny".into()),
+            settings: ThemeSettings {
+                foreground: Some(rgb(0x11, 0x22, 0x33)),
+                background: Some(AnsiColor::Default.into()),
+                ..ThemeSettings::default()
+            },
+            scopes: vec![
+                ThemeItem {
+                    scope: ScopeSelectors::from_str("comment, string - string.quoted").unwrap(),
+                    style: StyleModifier {
+                        foreground: Some(AnsiColor::Palette(8).into()),
+                        background: None,
+                        font_style: Some(FontStyle::ITALIC | FontStyle::BOLD),
+                    },
+                },
+                ThemeItem {
+                    scope: ScopeSelectors::from_str("source.c++ meta.block").unwrap(),
+                    style: StyleModifier {
+                        foreground: None,
+                        background: Some(Color {
+                            a: 0x80,
+                            ..rgb(0xff, 0x00, 0x00)
+                        }),
+                        font_style: None,
+                    },
+                },
+            ],
+            ..Theme::default()
+        }
+    }
+
+    #[test]
+    fn handwritten_theme() {
+        let theme = tiny_theme();
+        insta::assert_snapshot!(css_for_theme(&theme, ClassStyle::Spaced), @r"
+        /* Tiny */
+        .code {
+          color: #112233;
+        }
+        .code .code-line-number {
+          user-select: none;
+        }
+        .comment, .string {
+          color: var(--ansi-8, #7f7f7f);
+          font-weight: bold;
+          font-style: italic;
+        }
+        .source.c\2b \2b  .meta.block {
+          background-color: #ff000080;
+        }
+        ");
+
+        let mut scoped = String::new();
+        let style = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+        write_theme_css(&mut scoped, &theme, style, Some("[data-theme=\"tiny\"]"));
+        insta::assert_snapshot!(scoped, @r#"
+        /* Tiny */
+        [data-theme="tiny"] .hl-code {
+          color: #112233;
+        }
+        [data-theme="tiny"] .hl-code .hl-code-line-number {
+          user-select: none;
+        }
+        [data-theme="tiny"] .hl-comment, [data-theme="tiny"] .hl-string {
+          color: var(--ansi-8, #7f7f7f);
+          font-weight: bold;
+          font-style: italic;
+        }
+        [data-theme="tiny"] .hl-source.hl-c\2b \2b  .hl-meta.hl-block {
+          background-color: #ff000080;
+        }
+        "#);
+    }
+
+    #[test]
+    fn dual_themes() {
+        let light = tiny_theme();
+        let mut dark = tiny_theme();
+        dark.name = Some("Tiny Dark".into());
+        dark.settings.background = Some(rgb(0, 0, 0));
+        dark.scopes.truncate(1);
+        dark.scopes[0].style.font_style = Some(FontStyle::UNDERLINE);
+
+        insta::assert_snapshot!(dual_css_for_themes(&light, &dark, ClassStyle::Spaced), @r#"
+        /* light: Tiny */
+        .code {
+          --code-light-fg: #112233;
+        }
+        .comment, .string {
+          --code-light-fg: var(--ansi-8, #7f7f7f);
+          --code-light-font-weight: bold;
+          --code-light-font-style: italic;
+          --code-light-text-decoration: none;
+        }
+        .source.c\2b \2b  .meta.block {
+          --code-light-scope-bg: #ff000080;
+        }
+
+        /* dark: Tiny Dark */
+        .code {
+          --code-dark-fg: #112233;
+          --code-dark-bg: #000000;
+        }
+        .comment, .string {
+          --code-dark-fg: var(--ansi-8, #7f7f7f);
+          --code-dark-font-weight: normal;
+          --code-dark-font-style: normal;
+          --code-dark-text-decoration: underline;
+        }
+
+        /* Light by default */
+        .code {
+          color: var(--code-light-fg);
+          background-color: var(--code-light-bg);
+        }
+        .code span {
+          color: var(--code-light-fg);
+          background-color: var(--code-light-scope-bg, transparent);
+          font-weight: var(--code-light-font-weight, inherit);
+          font-style: var(--code-light-font-style, inherit);
+          text-decoration: var(--code-light-text-decoration, inherit);
+        }
+        .code .code-line-number {
+          color: var(--code-light-gutter-fg, inherit);
+          background-color: var(--code-light-gutter, transparent);
+          user-select: none;
+        }
+        .code .code-line-highlighted {
+          display: block;
+          background-color: var(--code-light-line-highlight, transparent);
+        }
+        @media (prefers-color-scheme: dark) {
+          .code {
+            color: var(--code-dark-fg);
+            background-color: var(--code-dark-bg);
+          }
+          .code span {
+            color: var(--code-dark-fg);
+            background-color: var(--code-dark-scope-bg, transparent);
+            font-weight: var(--code-dark-font-weight, inherit);
+            font-style: var(--code-dark-font-style, inherit);
+            text-decoration: var(--code-dark-text-decoration, inherit);
+          }
+          .code .code-line-number {
+            color: var(--code-dark-gutter-fg, inherit);
+            background-color: var(--code-dark-gutter, transparent);
+            user-select: none;
+          }
+          .code .code-line-highlighted {
+            display: block;
+            background-color: var(--code-dark-line-highlight, transparent);
+          }
+        }
+        /* Explicit overrides */
+        [data-color-scheme="light"] .code {
+          color: var(--code-light-fg);
+          background-color: var(--code-light-bg);
+        }
+        [data-color-scheme="light"] .code span {
+          color: var(--code-light-fg);
+          background-color: var(--code-light-scope-bg, transparent);
+          font-weight: var(--code-light-font-weight, inherit);
+          font-style: var(--code-light-font-style, inherit);
+          text-decoration: var(--code-light-text-decoration, inherit);
+        }
+        [data-color-scheme="light"] .code .code-line-number {
+          color: var(--code-light-gutter-fg, inherit);
+          background-color: var(--code-light-gutter, transparent);
+          user-select: none;
+        }
+        [data-color-scheme="light"] .code .code-line-highlighted {
+          display: block;
+          background-color: var(--code-light-line-highlight, transparent);
+        }
+        [data-color-scheme="dark"] .code {
+          color: var(--code-dark-fg);
+          background-color: var(--code-dark-bg);
+        }
+        [data-color-scheme="dark"] .code span {
+          color: var(--code-dark-fg);
+          background-color: var(--code-dark-scope-bg, transparent);
+          font-weight: var(--code-dark-font-weight, inherit);
+          font-style: var(--code-dark-font-style, inherit);
+          text-decoration: var(--code-dark-text-decoration, inherit);
+        }
+        [data-color-scheme="dark"] .code .code-line-number {
+          color: var(--code-dark-gutter-fg, inherit);
+          background-color: var(--code-dark-gutter, transparent);
+          user-select: none;
+        }
+        [data-color-scheme="dark"] .code .code-line-highlighted {
+          display: block;
+          background-color: var(--code-dark-line-highlight, transparent);
+        }
+        "#);
+    }
+}
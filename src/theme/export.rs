@@ -0,0 +1,646 @@
+//! Exports themes to the theme formats of other editors
+//!
+//! TextMate scopes don't map one-to-one onto the highlight groups of other editors, so each
+//! supported group is resolved from a representative scope (e.g. Helix's `function` comes from
+//! `entity.name.function`). Any of the theme's scope selectors that don't affect any of the
+//! supported groups are listed in [`Export::unmapped`]
+//!
+//! Palette colors used by the terminal themes are resolved with
+//! [`AnsiPalette::XTERM`][crate::html::AnsiPalette::XTERM] and translucent colors are blended
+//! with the background since none of the formats handle them consistently
+//!
+//! # Example
+//!
+//! ```
+//! use two_face::theme::{export, extra, EmbeddedThemeName};
+//!
+//! let theme_set = extra();
+//! let export = export::helix(&theme_set[EmbeddedThemeName::Nord]);
+//! assert!(export.contents.contains("\n\"comment\" = { fg = \"#616e88\" }\n"));
+//! for selector in &export.unmapped {
+//!     eprintln!("No equivalent for `{selector}`");
+//! }
+//! ```
+
+use std::fmt::Write;
+
+use syntect::highlighting::{Color, FontStyle, Highlighter, StyleModifier, Theme, ThemeSettings};
+use syntect::parsing::Scope;
+
+use super::transform::selector_text;
+use crate::{color, html::AnsiPalette};
+
+/// A highlight group resolved from a representative TextMate scope
+struct Group {
+    scope: &'static str,
+    helix: Option<&'static str>,
+    neovim: Option<&'static str>,
+    zed: Option<&'static str>,
+}
+
+/// A group with its Helix, Neovim, and Zed names where an empty name means there's no equivalent
+const fn group(
+    scope: &'static str,
+    helix: &'static str,
+    neovim: &'static str,
+    zed: &'static str,
+) -> Group {
+    const fn name(name: &'static str) -> Option<&'static str> {
+        if name.is_empty() {
+            None
+        } else {
+            Some(name)
+        }
+    }
+
+    Group {
+        scope,
+        helix: name(helix),
+        neovim: name(neovim),
+        zed: name(zed),
+    }
+}
+
+const GROUPS: &[Group] = &[
+    group("comment", "comment", "@comment", "comment"),
+    group("string", "string", "@string", "string"),
+    group(
+        "string.regexp",
+        "string.regexp",
+        "@string.regexp",
+        "string.regex",
+    ),
+    group(
+        "constant.character.escape",
+        "constant.character.escape",
+        "@string.escape",
+        "string.escape",
+    ),
+    group("constant.numeric", "constant.numeric", "@number", "number"),
+    group(
+        "constant.language",
+        "constant.builtin",
+        "@constant.builtin",
+        "boolean",
+    ),
+    group("constant", "constant", "@constant", "constant"),
+    group("keyword", "keyword", "@keyword", "keyword"),
+    group(
+        "keyword.control",
+        "keyword.control",
+        "@keyword.conditional",
+        "",
+    ),
+    group("keyword.operator", "operator", "@operator", "operator"),
+    group("storage.type", "keyword.storage.type", "@keyword.type", ""),
+    group(
+        "storage.modifier",
+        "keyword.storage.modifier",
+        "@keyword.modifier",
+        "",
+    ),
+    group("entity.name.function", "function", "@function", "function"),
+    group(
+        "support.function",
+        "function.builtin",
+        "@function.builtin",
+        "",
+    ),
+    group("entity.name.type", "type", "@type", "type"),
+    group("support.type", "type.builtin", "@type.builtin", ""),
+    group("entity.name.namespace", "namespace", "@module", ""),
+    group("variable", "variable", "@variable", "variable"),
+    group(
+        "variable.parameter",
+        "variable.parameter",
+        "@variable.parameter",
+        "",
+    ),
+    group(
+        "variable.language",
+        "variable.builtin",
+        "@variable.builtin",
+        "variable.special",
+    ),
+    group("entity.name.tag", "tag", "@tag", "tag"),
+    group(
+        "entity.other.attribute-name",
+        "attribute",
+        "@attribute",
+        "attribute",
+    ),
+    group("punctuation", "punctuation", "@punctuation", "punctuation"),
+    group(
+        "markup.heading",
+        "markup.heading",
+        "@markup.heading",
+        "title",
+    ),
+    group(
+        "markup.bold",
+        "markup.bold",
+        "@markup.strong",
+        "emphasis.strong",
+    ),
+    group(
+        "markup.italic",
+        "markup.italic",
+        "@markup.italic",
+        "emphasis",
+    ),
+    group(
+        "markup.underline.link",
+        "markup.link.url",
+        "@markup.link.url",
+        "link_uri",
+    ),
+    group("markup.quote", "markup.quote", "@markup.quote", ""),
+    group("markup.raw", "markup.raw", "@markup.raw", "text.literal"),
+    group("markup.inserted", "diff.plus", "@diff.plus", ""),
+    group("markup.deleted", "diff.minus", "@diff.minus", ""),
+    group("markup.changed", "diff.delta", "@diff.delta", ""),
+];
+
+/// An exported theme
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Export {
+    /// The contents of the exported theme file
+    pub contents: String,
+    /// The theme's scope selectors that don't have an equivalent in the exported format
+    pub unmapped: Vec<String>,
+}
+
+/// A resolved group style
+struct GroupStyle {
+    foreground: Option<Color>,
+    background: Option<Color>,
+    font_style: FontStyle,
+}
+
+/// Resolves the styles for all of the groups that have a name in the target format
+struct Groups<'theme> {
+    theme: &'theme Theme,
+    styles: Vec<(&'static str, GroupStyle)>,
+    unmapped: Vec<String>,
+}
+
+impl<'theme> Groups<'theme> {
+    fn new(theme: &'theme Theme, name: impl Fn(&Group) -> Option<&'static str>) -> Self {
+        let highlighter = Highlighter::new(theme);
+        let scopes: Vec<_> = GROUPS
+            .iter()
+            .filter_map(|group| {
+                let scope = Scope::new(group.scope).expect("Valid scope");
+                name(group).map(|name| (name, scope))
+            })
+            .collect();
+
+        let styles = scopes
+            .iter()
+            .filter_map(|&(name, scope)| {
+                let StyleModifier {
+                    foreground,
+                    background,
+                    font_style,
+                } = highlighter.style_mod_for_stack(&[scope]);
+                let style = GroupStyle {
+                    foreground: resolve(foreground, &theme.settings),
+                    background: resolve(background, &theme.settings),
+                    font_style: font_style.unwrap_or_else(FontStyle::empty),
+                };
+                let is_empty = style.foreground.is_none()
+                    && style.background.is_none()
+                    && style.font_style.is_empty();
+                (!is_empty).then_some((name, style))
+            })
+            .collect();
+
+        let mut unmapped = Vec::new();
+        for selector in theme.scopes.iter().flat_map(|item| &item.scope.selectors) {
+            let is_mapped = scopes
+                .iter()
+                .any(|(_, scope)| selector.does_match(&[*scope]).is_some());
+            let text = selector_text(selector);
+            if !is_mapped && !unmapped.contains(&text) {
+                unmapped.push(text);
+            }
+        }
+
+        Self {
+            theme,
+            styles,
+            unmapped,
+        }
+    }
+
+    fn settings(&self) -> &ThemeSettings {
+        &self.theme.settings
+    }
+
+    fn color(&self, color: Option<Color>) -> Option<Color> {
+        resolve(color, self.settings())
+    }
+
+    fn name(&self) -> &str {
+        self.theme.name.as_deref().unwrap_or("Unnamed")
+    }
+
+    fn is_dark(&self) -> bool {
+        self.color(self.settings().background)
+            .map_or(true, |background| color::lightness(background) < 50.0)
+    }
+}
+
+/// Exports `theme` as a [Helix](https://helix-editor.com) TOML theme
+pub fn helix(theme: &Theme) -> Export {
+    let groups = Groups::new(theme, |group| group.helix);
+    let settings = groups.settings();
+    let mut out = String::new();
+    writeln!(out, "# {}", groups.name().replace(['\r', '\n'], " ")).expect("Infallible");
+
+    let ui = [
+        ("ui.background", None, settings.background),
+        ("ui.text", settings.foreground, None),
+        ("ui.cursor", settings.background, settings.caret),
+        ("ui.selection", None, settings.selection),
+        ("ui.cursorline.primary", None, settings.line_highlight),
+        ("ui.linenr", settings.gutter_foreground, settings.gutter),
+    ];
+    let ui: Vec<_> = ui
+        .into_iter()
+        .map(|(name, foreground, background)| {
+            let style = GroupStyle {
+                foreground: groups.color(foreground),
+                background: groups.color(background),
+                font_style: FontStyle::empty(),
+            };
+            (name, style)
+        })
+        .collect();
+    for (name, style) in ui.iter().chain(&groups.styles) {
+        let mut fields = Vec::new();
+        if let Some(color) = style.foreground {
+            fields.push(format!("fg = \"{}\"", hex(color)));
+        }
+        if let Some(color) = style.background {
+            fields.push(format!("bg = \"{}\"", hex(color)));
+        }
+        let modifiers: Vec<_> = [
+            (FontStyle::BOLD, "\"bold\""),
+            (FontStyle::ITALIC, "\"italic\""),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| style.font_style.contains(*flag))
+        .map(|(_, modifier)| modifier)
+        .collect();
+        if !modifiers.is_empty() {
+            fields.push(format!("modifiers = [{}]", modifiers.join(", ")));
+        }
+        if style.font_style.contains(FontStyle::UNDERLINE) {
+            fields.push("underline = { style = \"line\" }".to_owned());
+        }
+        if !fields.is_empty() {
+            writeln!(out, "\"{name}\" = {{ {} }}", fields.join(", ")).expect("Infallible");
+        }
+    }
+
+    Export {
+        contents: out,
+        unmapped: groups.unmapped,
+    }
+}
+
+/// Exports `theme` as a [Neovim](https://neovim.io) colorscheme written in Lua
+///
+/// Syntax highlighting uses the treesitter highlight groups
+pub fn neovim(theme: &Theme) -> Export {
+    let groups = Groups::new(theme, |group| group.neovim);
+    let settings = groups.settings();
+    let mut out = String::new();
+    writeln!(out, "-- {}", groups.name().replace(['\r', '\n'], " ")).expect("Infallible");
+    out.push_str("vim.cmd(\"highlight clear\")\n");
+    let background = if groups.is_dark() { "dark" } else { "light" };
+    writeln!(out, "vim.o.background = \"{background}\"").expect("Infallible");
+    out.push_str("vim.g.colors_name = ");
+    push_lua_string(&mut out, &colors_name(groups.name()));
+    out.push_str("\n\nlocal set = vim.api.nvim_set_hl\n");
+
+    let ui = [
+        ("Normal", settings.foreground, settings.background),
+        ("Cursor", settings.background, settings.caret),
+        ("Visual", None, settings.selection),
+        ("CursorLine", None, settings.line_highlight),
+        ("LineNr", settings.gutter_foreground, settings.gutter),
+    ];
+    for (name, foreground, background) in ui {
+        let style = GroupStyle {
+            foreground: groups.color(foreground),
+            background: groups.color(background),
+            font_style: FontStyle::empty(),
+        };
+        write_neovim_group(&mut out, name, &style);
+    }
+    out.push('\n');
+    for (name, style) in &groups.styles {
+        write_neovim_group(&mut out, name, style);
+    }
+
+    Export {
+        contents: out,
+        unmapped: groups.unmapped,
+    }
+}
+
+fn write_neovim_group(out: &mut String, name: &str, style: &GroupStyle) {
+    let mut fields = Vec::new();
+    if let Some(color) = style.foreground {
+        fields.push(format!("fg = \"{}\"", hex(color)));
+    }
+    if let Some(color) = style.background {
+        fields.push(format!("bg = \"{}\"", hex(color)));
+    }
+    for (flag, field) in [
+        (FontStyle::BOLD, "bold = true"),
+        (FontStyle::ITALIC, "italic = true"),
+        (FontStyle::UNDERLINE, "underline = true"),
+    ] {
+        if style.font_style.contains(flag) {
+            fields.push(field.to_owned());
+        }
+    }
+    if !fields.is_empty() {
+        writeln!(out, "set(0, \"{name}\", {{ {} }})", fields.join(", ")).expect("Infallible");
+    }
+}
+
+/// Exports `theme` as a [Zed](https://zed.dev) theme family containing just the one theme
+///
+/// Zed doesn't support underlined syntax highlighting, so underlines get dropped
+pub fn zed(theme: &Theme) -> Export {
+    let groups = Groups::new(theme, |group| group.zed);
+    let settings = groups.settings();
+    let mut out = String::from("{\n");
+    out.push_str("  \"$schema\": \"https://zed.dev/schema/themes/v0.2.0.json\",\n");
+    out.push_str("  \"name\": ");
+    push_json_string(&mut out, groups.name());
+    out.push_str(",\n  \"author\": ");
+    push_json_string(&mut out, theme.author.as_deref().unwrap_or(""));
+    out.push_str(",\n  \"themes\": [\n    {\n      \"name\": ");
+    push_json_string(&mut out, groups.name());
+    let appearance = if groups.is_dark() { "dark" } else { "light" };
+    writeln!(out, ",\n      \"appearance\": \"{appearance}\",").expect("Infallible");
+    out.push_str("      \"style\": {\n");
+
+    let ui = [
+        ("background", settings.background),
+        ("editor.background", settings.background),
+        ("editor.foreground", settings.foreground),
+        ("text", settings.foreground),
+        ("editor.active_line.background", settings.line_highlight),
+        ("editor.gutter.background", settings.gutter),
+        ("editor.line_number", settings.gutter_foreground),
+    ];
+    for (key, color) in ui {
+        if let Some(color) = groups.color(color) {
+            writeln!(out, "        \"{key}\": \"{}\",", hex(color)).expect("Infallible");
+        }
+    }
+    let cursor = groups.color(settings.caret);
+    let selection = groups.color(settings.selection);
+    if cursor.is_some() || selection.is_some() {
+        let mut fields = Vec::new();
+        if let Some(color) = cursor {
+            fields.push(format!("\"cursor\": \"{}\"", hex(color)));
+        }
+        if let Some(color) = selection {
+            fields.push(format!("\"selection\": \"{}\"", hex(color)));
+        }
+        writeln!(out, "        \"players\": [{{ {} }}],", fields.join(", ")).expect("Infallible");
+    }
+
+    out.push_str("        \"syntax\": {");
+    let mut separator = "";
+    for (name, style) in &groups.styles {
+        let mut fields = Vec::new();
+        if let Some(color) = style.foreground {
+            fields.push(format!("\"color\": \"{}\"", hex(color)));
+        }
+        if let Some(color) = style.background {
+            fields.push(format!("\"background_color\": \"{}\"", hex(color)));
+        }
+        if style.font_style.contains(FontStyle::ITALIC) {
+            fields.push("\"font_style\": \"italic\"".to_owned());
+        }
+        if style.font_style.contains(FontStyle::BOLD) {
+            fields.push("\"font_weight\": 700".to_owned());
+        }
+        if fields.is_empty() {
+            continue;
+        }
+        write!(
+            out,
+            "{separator}\n          \"{name}\": {{ {} }}",
+            fields.join(", ")
+        )
+        .expect("Infallible");
+        separator = ",";
+    }
+    out.push_str("\n        }\n      }\n    }\n  ]\n}\n");
+
+    Export {
+        contents: out,
+        unmapped: groups.unmapped,
+    }
+}
+
+fn resolve(color: Option<Color>, settings: &ThemeSettings) -> Option<Color> {
+    color.and_then(|color| AnsiPalette::XTERM.resolve(color, settings.background))
+}
+
+fn hex(Color { r, g, b, .. }: Color) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// A lowercase, dash-separated version of the theme's name
+fn colors_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    let words: Vec<_> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.join("-")
+}
+
+fn push_lua_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\0'..='\x1f' => write!(out, "\\{:03}", u32::from(c)).expect("Infallible"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+pub(super) fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0'..='\x1f' => write!(out, "\\u{:04x}", u32::from(c)).expect("Infallible"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use syntect::highlighting::{ScopeSelectors, ThemeItem};
+
+    use crate::{color::rgb, terminal::AnsiColor};
+
+    fn tiny_theme() -> Theme {
+        let item = |scope, foreground, font_style| ThemeItem {
+            scope: ScopeSelectors::from_str(scope).unwrap(),
+            style: StyleModifier {
+                foreground,
+                background: None,
+                font_style,
+            },
+        };
+        Theme {
+            name: Some("Tiny \"Theme\"".into()),
+            author: Some("Someone".into()),
+            settings: ThemeSettings {
+                foreground: Some(rgb(0x11, 0x22, 0x33)),
+                background: Some(rgb(0xfa, 0xfa, 0xfa)),
+                selection: Some(Color {
+                    a: 0x80,
+                    ..rgb(0x00, 0x00, 0xff)
+                }),
+                ..ThemeSettings::default()
+            },
+            scopes: vec![
+                item(
+                    "comment, meta.block - string",
+                    Some(AnsiColor::Palette(8).into()),
+                    Some(FontStyle::ITALIC),
+                ),
+                item(
+                    "entity.name",
+                    Some(rgb(0xaa, 0x00, 0x00)),
+                    Some(FontStyle::BOLD | FontStyle::UNDERLINE),
+                ),
+                item("source.rust keyword", Some(rgb(0x00, 0xaa, 0x00)), None),
+            ],
+        }
+    }
+
+    #[test]
+    fn helix_export() {
+        let export = helix(&tiny_theme());
+        insta::assert_snapshot!(export.contents, @r##"
+        # Tiny "Theme"
+        "ui.background" = { bg = "#fafafa" }
+        "ui.text" = { fg = "#112233" }
+        "ui.cursor" = { fg = "#fafafa" }
+        "ui.selection" = { bg = "#7d7dfd" }
+        "comment" = { fg = "#7f7f7f", modifiers = ["italic"] }
+        "function" = { fg = "#aa0000", modifiers = ["bold"], underline = { style = "line" } }
+        "type" = { fg = "#aa0000", modifiers = ["bold"], underline = { style = "line" } }
+        "namespace" = { fg = "#aa0000", modifiers = ["bold"], underline = { style = "line" } }
+        "tag" = { fg = "#aa0000", modifiers = ["bold"], underline = { style = "line" } }
+        "##);
+        assert_eq!(
+            export.unmapped,
+            ["meta.block - string", "source.rust keyword"]
+        );
+    }
+
+    #[test]
+    fn neovim_export() {
+        let export = neovim(&tiny_theme());
+        insta::assert_snapshot!(export.contents, @r##"
+        -- Tiny "Theme"
+        vim.cmd("highlight clear")
+        vim.o.background = "light"
+        vim.g.colors_name = "tiny-theme"
+
+        local set = vim.api.nvim_set_hl
+        set(0, "Normal", { fg = "#112233", bg = "#fafafa" })
+        set(0, "Cursor", { fg = "#fafafa" })
+        set(0, "Visual", { bg = "#7d7dfd" })
+
+        set(0, "@comment", { fg = "#7f7f7f", italic = true })
+        set(0, "@function", { fg = "#aa0000", bold = true, underline = true })
+        set(0, "@type", { fg = "#aa0000", bold = true, underline = true })
+        set(0, "@module", { fg = "#aa0000", bold = true, underline = true })
+        set(0, "@tag", { fg = "#aa0000", bold = true, underline = true })
+        "##);
+    }
+
+    #[test]
+    fn zed_export() {
+        let export = zed(&tiny_theme());
+        insta::assert_snapshot!(export.contents, @r##"
+        {
+          "$schema": "https://zed.dev/schema/themes/v0.2.0.json",
+          "name": "Tiny \"Theme\"",
+          "author": "Someone",
+          "themes": [
+            {
+              "name": "Tiny \"Theme\"",
+              "appearance": "light",
+              "style": {
+                "background": "#fafafa",
+                "editor.background": "#fafafa",
+                "editor.foreground": "#112233",
+                "text": "#112233",
+                "players": [{ "selection": "#7d7dfd" }],
+                "syntax": {
+                  "comment": { "color": "#7f7f7f", "font_style": "italic" },
+                  "function": { "color": "#aa0000", "font_weight": 700 },
+                  "type": { "color": "#aa0000", "font_weight": 700 },
+                  "tag": { "color": "#aa0000", "font_weight": 700 }
+                }
+              }
+            }
+          ]
+        }
+        "##);
+    }
+
+    #[test]
+    fn every_embedded_theme_parses() {
+        let theme_set = crate::theme::extra();
+        for &name in crate::theme::EmbeddedLazyThemeSet::theme_names() {
+            let theme = &theme_set[name];
+            let helix = helix(theme);
+            toml::from_str::<toml::Table>(&helix.contents)
+                .unwrap_or_else(|err| panic!("{name:?}: {err}"));
+            let zed = zed(theme);
+            serde_json::from_str::<serde_json::Value>(&zed.contents)
+                .unwrap_or_else(|err| panic!("{name:?}: {err}"));
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(colors_name("Solarized (dark)"), "solarized-dark");
+        let mut lua = String::new();
+        push_lua_string(&mut lua, "a\"b\\c\n\x01");
+        assert_eq!(lua, r#""a\"b\\c\n\001""#);
+    }
+}
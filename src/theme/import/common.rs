@@ -0,0 +1,117 @@
+//! Helpers shared between the importers
+
+use std::{fmt, io};
+
+use syntect::highlighting::Color;
+
+/// Everything that can go wrong when importing a theme
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading the theme failed
+    Io(io::Error),
+    /// The theme isn't valid JSON or doesn't have the expected structure
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed reading theme: {err}"),
+            Self::Json(err) => write!(f, "invalid theme: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// Parses a `#RGB`, `#RGBA`, `#RRGGBB`, or `#RRGGBBAA` color
+pub(super) fn parse_hex_color(s: &str) -> Option<Color> {
+    let hex = s.trim().strip_prefix('#')?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap() * 0x11;
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    let (r, g, b, a) = match hex.len() {
+        3 => (digit(0), digit(1), digit(2), 0xff),
+        4 => (digit(0), digit(1), digit(2), digit(3)),
+        6 => (byte(0), byte(2), byte(4), 0xff),
+        8 => (byte(0), byte(2), byte(4), byte(6)),
+        _ => return None,
+    };
+    Some(Color { r, g, b, a })
+}
+
+/// Blanks out comments and trailing commas so that JSONC can be parsed as plain JSON
+///
+/// Everything gets replaced with spaces (keeping newlines) so that error locations still line up
+/// with the original text
+pub(super) fn strip_jsonc(s: &str) -> String {
+    let mut bytes = s.as_bytes().to_vec();
+    let mut i = 0;
+    // The position of a comma that's only been followed by whitespace and comments so far
+    let mut pending_comma = None;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                pending_comma = None;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    bytes[i] = b' ';
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = s[i + 2..].find("*/").map_or(bytes.len(), |end| i + end + 4);
+                for byte in &mut bytes[i..end] {
+                    if *byte != b'\n' {
+                        *byte = b' ';
+                    }
+                }
+                i = end;
+                continue;
+            }
+            b',' => pending_comma = Some(i),
+            b'}' | b']' => {
+                if let Some(comma) = pending_comma.take() {
+                    bytes[comma] = b' ';
+                }
+            }
+            b if b.is_ascii_whitespace() => {}
+            _ => pending_comma = None,
+        }
+        i += 1;
+    }
+
+    // Only ASCII bytes got replaced with other ASCII bytes
+    String::from_utf8(bytes).expect("Still valid UTF-8")
+}
@@ -0,0 +1,55 @@
+//! Converts themes from other editors' formats into [`syntect::highlighting::Theme`]s
+//!
+//! The converted themes can be used anywhere a [`Theme`] is expected, or added to a
+//! [`ThemeSet`][syntect::highlighting::ThemeSet] that gets converted into a
+//! [`LazyThemeSet`][super::LazyThemeSet]
+//!
+//! [`Theme`]: syntect::highlighting::Theme
+
+mod common;
+pub mod sublime;
+pub mod vscode;
+
+pub use common::Error;
+
+#[cfg(test)]
+mod tests {
+    use super::common::*;
+
+    use syntect::highlighting::Color;
+
+    #[test]
+    fn hex_colors() {
+        let rgba = |r, g, b, a| Some(Color { r, g, b, a });
+        assert_eq!(parse_hex_color("#abc"), rgba(0xaa, 0xbb, 0xcc, 0xff));
+        assert_eq!(parse_hex_color("#abcd"), rgba(0xaa, 0xbb, 0xcc, 0xdd));
+        assert_eq!(parse_hex_color("#123456"), rgba(0x12, 0x34, 0x56, 0xff));
+        assert_eq!(parse_hex_color(" #12345678"), rgba(0x12, 0x34, 0x56, 0x78));
+        assert_eq!(parse_hex_color("#12345"), None);
+        assert_eq!(parse_hex_color("#ghijkl"), None);
+        assert_eq!(parse_hex_color("#ééé"), None);
+        assert_eq!(parse_hex_color("123456"), None);
+    }
+
+    #[test]
+    fn jsonc() {
+        let jsonc = r#"{
+    // A comment with a "quote"
+    "url": "https://example.com", /* block
+    comment */ "list": [1, 2, /* trailing */ ],
+    "escaped": "\" // not a comment",
+}"#;
+        let json = strip_jsonc(jsonc);
+        assert_eq!(json.len(), jsonc.len());
+        insta::assert_snapshot!(json, @r#"
+        {
+                                       
+            "url": "https://example.com",         
+                       "list": [1, 2                 ],
+            "escaped": "\" // not a comment" 
+        }
+        "#);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["escaped"], "\" // not a comment");
+    }
+}
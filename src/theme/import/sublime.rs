@@ -0,0 +1,647 @@
+//! Imports Sublime Text's JSON `.sublime-color-scheme` color schemes
+//!
+//! Color schemes can define `variables` that get referenced with `var()` in the scheme's
+//! `globals` and `rules`, and adjust colors with the `color()` function e.g.
+//! `color(var(blue) alpha(0.5))`. The supported adjusters are `alpha()`/`a()`,
+//! `saturation()`/`s()`, `lightness()`/`l()`, `blend()`, `blenda()`, and `min-contrast()`. The
+//! `globals` share their names with [`ThemeSettings`]'s fields
+
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+
+use serde_derive::Deserialize;
+use syntect::highlighting::{
+    Color, FontStyle, ScopeSelectors, StyleModifier, Theme, ThemeItem, ThemeSettings,
+    UnderlineOption,
+};
+
+use super::common::{parse_hex_color, strip_jsonc, Error};
+
+#[derive(Deserialize)]
+struct ColorScheme {
+    name: Option<String>,
+    author: Option<String>,
+    #[serde(default)]
+    variables: BTreeMap<String, String>,
+    #[serde(default)]
+    globals: BTreeMap<String, String>,
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+#[derive(Deserialize)]
+struct Rule {
+    scope: Option<String>,
+    foreground: Option<Foreground>,
+    background: Option<String>,
+    font_style: Option<String>,
+}
+
+/// Hashed syntax highlighting uses a list of colors for the foreground
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Foreground {
+    Solid(String),
+    Hashed(Vec<String>),
+}
+
+/// Parses the contents of a `.sublime-color-scheme` file
+///
+/// `var()`s are resolved from the scheme's `variables` and `color()` adjusters get applied, so
+/// the theme only contains plain colors. Values that can't be resolved are skipped like Sublime
+/// Text does, and hashed foregrounds use their first color
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::import::sublime;
+///
+/// let theme = sublime::parse(r##"{
+///     "name": "Tiny",
+///     "variables": {
+///         "blue": "hsl(210, 50%, 60%)",
+///         "black": "#1e1e1e",
+///     },
+///     "globals": {
+///         "background": "var(black)",
+///         "line_highlight": "color(var(blue) alpha(0.25))",
+///     },
+///     "rules": [
+///         { "scope": "keyword", "foreground": "var(blue)", "font_style": "bold" },
+///     ],
+/// }"##).unwrap();
+/// assert_eq!(theme.settings.line_highlight.unwrap().a, 0x40);
+/// assert_eq!(theme.scopes.len(), 1);
+/// ```
+pub fn parse(s: &str) -> Result<Theme, Error> {
+    let scheme: ColorScheme = serde_json::from_str(&strip_jsonc(s))?;
+    Ok(scheme.into())
+}
+
+/// Reads and parses a `.sublime-color-scheme` file
+///
+/// See [`parse()`] for the details
+///
+/// # Example
+///
+/// ```no_run
+/// use two_face::theme::import::sublime;
+///
+/// let theme = sublime::load_from_file("Mariana.sublime-color-scheme")?;
+/// # Ok::<_, two_face::theme::import::Error>(())
+/// ```
+pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Theme, Error> {
+    let contents = fs::read_to_string(path)?;
+    parse(&contents)
+}
+
+impl From<ColorScheme> for Theme {
+    fn from(scheme: ColorScheme) -> Self {
+        let ColorScheme {
+            name,
+            author,
+            variables,
+            globals,
+            rules,
+        } = scheme;
+        let resolver = Resolver {
+            variables: &variables,
+        };
+        let color = |key: &str| resolver.color(globals.get(key)?);
+
+        let mut settings = ThemeSettings::default();
+        let colors = [
+            ("foreground", &mut settings.foreground),
+            ("background", &mut settings.background),
+            ("caret", &mut settings.caret),
+            ("line_highlight", &mut settings.line_highlight),
+            ("misspelling", &mut settings.misspelling),
+            ("minimap_border", &mut settings.minimap_border),
+            ("accent", &mut settings.accent),
+            (
+                "bracket_contents_foreground",
+                &mut settings.bracket_contents_foreground,
+            ),
+            ("brackets_foreground", &mut settings.brackets_foreground),
+            ("brackets_background", &mut settings.brackets_background),
+            ("tags_foreground", &mut settings.tags_foreground),
+            ("highlight", &mut settings.highlight),
+            ("find_highlight", &mut settings.find_highlight),
+            (
+                "find_highlight_foreground",
+                &mut settings.find_highlight_foreground,
+            ),
+            ("gutter", &mut settings.gutter),
+            ("gutter_foreground", &mut settings.gutter_foreground),
+            ("selection", &mut settings.selection),
+            ("selection_foreground", &mut settings.selection_foreground),
+            ("selection_border", &mut settings.selection_border),
+            ("inactive_selection", &mut settings.inactive_selection),
+            (
+                "inactive_selection_foreground",
+                &mut settings.inactive_selection_foreground,
+            ),
+            ("guide", &mut settings.guide),
+            ("active_guide", &mut settings.active_guide),
+            ("stack_guide", &mut settings.stack_guide),
+            ("shadow", &mut settings.shadow),
+        ];
+        for (key, field) in colors {
+            *field = color(key);
+        }
+        let options = [
+            (
+                "bracket_contents_options",
+                &mut settings.bracket_contents_options,
+            ),
+            ("brackets_options", &mut settings.brackets_options),
+            ("tags_options", &mut settings.tags_options),
+        ];
+        for (key, field) in options {
+            *field = globals
+                .get(key)
+                .and_then(|options| underline_option(options));
+        }
+        settings.popup_css = globals.get("popup_css").cloned();
+        settings.phantom_css = globals.get("phantom_css").cloned();
+
+        let scopes = rules
+            .into_iter()
+            .filter_map(|rule| {
+                let scope = ScopeSelectors::from_str(rule.scope.as_deref()?).ok()?;
+                let foreground = rule.foreground.and_then(|foreground| match foreground {
+                    Foreground::Solid(color) => resolver.color(&color),
+                    Foreground::Hashed(colors) => resolver.color(colors.first()?),
+                });
+                let style = StyleModifier {
+                    foreground,
+                    background: rule.background.and_then(|color| resolver.color(&color)),
+                    font_style: rule.font_style.as_deref().map(font_style),
+                };
+                Some(ThemeItem { scope, style })
+            })
+            .collect();
+
+        Self {
+            name,
+            author,
+            settings,
+            scopes,
+        }
+    }
+}
+
+fn font_style(s: &str) -> FontStyle {
+    s.split_whitespace()
+        .fold(FontStyle::empty(), |acc, word| match word {
+            "bold" => acc | FontStyle::BOLD,
+            "italic" => acc | FontStyle::ITALIC,
+            "underline" | "stippled_underline" | "squiggly_underline" => acc | FontStyle::UNDERLINE,
+            // `glow` has no equivalent
+            _ => acc,
+        })
+}
+
+/// Picks the underline style out of options like `"foreground squiggly_underline"`
+fn underline_option(s: &str) -> Option<UnderlineOption> {
+    s.split_whitespace().find_map(|word| match word {
+        "underline" => Some(UnderlineOption::Underline),
+        "stippled_underline" => Some(UnderlineOption::StippledUnderline),
+        "squiggly_underline" => Some(UnderlineOption::SquigglyUnderline),
+        _ => None,
+    })
+}
+
+/// RGBA with every channel in `0.0..=1.0`
+type Rgba = [f32; 4];
+
+/// Resolves CSS colors along with Sublime's `var()` and `color()` extensions
+struct Resolver<'a> {
+    variables: &'a BTreeMap<String, String>,
+}
+
+impl Resolver<'_> {
+    /// Guards against variables that (indirectly) reference themselves
+    const MAX_DEPTH: usize = 32;
+
+    fn color(&self, value: &str) -> Option<Color> {
+        let [r, g, b, a] = self.rgba(value, 0)?;
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Some(Color {
+            r: channel(r),
+            g: channel(g),
+            b: channel(b),
+            a: channel(a),
+        })
+    }
+
+    fn rgba(&self, value: &str, depth: usize) -> Option<Rgba> {
+        if depth > Self::MAX_DEPTH {
+            return None;
+        }
+        let value = value.trim();
+        let Some((func, args)) = call(value) else {
+            return parse_hex_color(value)
+                .map(|Color { r, g, b, a }| [r, g, b, a].map(|c| f32::from(c) / 255.0))
+                .or_else(|| named_color(value));
+        };
+        let args = split_args(args);
+        match (func, args.as_slice()) {
+            ("var", [name]) => self.rgba(self.variables.get(*name)?, depth + 1),
+            ("rgb" | "rgba", [r, g, b, rest @ ..]) => {
+                let channel = |c: &str| match c.strip_suffix('%') {
+                    Some(percent) => Some(percent.parse::<f32>().ok()? / 100.0),
+                    None => Some(c.parse::<f32>().ok()? / 255.0),
+                };
+                Some([channel(r)?, channel(g)?, channel(b)?, alpha(rest)?])
+            }
+            ("hsl" | "hsla", [h, s, l, rest @ ..]) => {
+                let hsl = [hue(h)?, percentage(s)?, percentage(l)?];
+                Some(from_hsl(hsl, alpha(rest)?))
+            }
+            ("hwb", [h, white, black, rest @ ..]) => {
+                let (white, black) = (percentage(white)?, percentage(black)?);
+                if white + black >= 1.0 {
+                    let gray = white / (white + black);
+                    return Some([gray, gray, gray, alpha(rest)?]);
+                }
+                let [r, g, b, a] = from_hsl([hue(h)?, 1.0, 0.5], alpha(rest)?);
+                let scale = |c: f32| c * (1.0 - white - black) + white;
+                Some([scale(r), scale(g), scale(b), a])
+            }
+            ("color", [base, adjusters @ ..]) => {
+                let base = self.rgba(base, depth + 1)?;
+                adjusters.iter().try_fold(base, |color, adjuster| {
+                    self.adjust(color, adjuster, depth + 1)
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Applies a single `color()` adjuster e.g. `alpha(0.5)` or `blend(var(bg) 40%)`
+    fn adjust(&self, color: Rgba, adjuster: &str, depth: usize) -> Option<Rgba> {
+        let (func, args) = call(adjuster)?;
+        match func {
+            "alpha" | "a" => {
+                let [r, g, b, a] = color;
+                Some([r, g, b, Amount::parse(args)?.apply(a)])
+            }
+            "saturation" | "s" | "lightness" | "l" => {
+                let [h, mut s, mut l] = to_hsl(color);
+                let amount = Amount::parse(args)?;
+                if func.starts_with('s') {
+                    s = amount.apply(s);
+                } else {
+                    l = amount.apply(l);
+                }
+                Some(from_hsl([h, s, l], color[3]))
+            }
+            "blend" | "blenda" => {
+                let [other, percent] = split_args(args)[..] else {
+                    return None;
+                };
+                let other = self.rgba(other, depth + 1)?;
+                // The percentage is how much of the base color to keep
+                let keep = percentage(percent)?;
+                let mut mixed = [0.0; 4];
+                for (i, channel) in mixed.iter_mut().enumerate() {
+                    *channel = color[i] * keep + other[i] * (1.0 - keep);
+                }
+                if func == "blend" {
+                    mixed[3] = color[3];
+                }
+                Some(mixed)
+            }
+            "min-contrast" => {
+                let [other, ratio] = split_args(args)[..] else {
+                    return None;
+                };
+                let other = self.rgba(other, depth + 1)?;
+                let ratio = ratio.parse::<f32>().ok()?;
+                Some(min_contrast(color, other, ratio))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Splits `name(args)` into its parts
+fn call(s: &str) -> Option<(&str, &str)> {
+    let (name, rest) = s.split_once('(')?;
+    let args = rest.strip_suffix(')')?;
+    let is_name = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    is_name.then_some((name, args))
+}
+
+/// Splits on whitespace, commas, and slashes that aren't nested in parentheses
+fn split_args(s: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' | '/' if depth == 0 => {
+                args.push(&s[start..i]);
+                start = i + 1;
+            }
+            c if c.is_whitespace() && depth == 0 => {
+                args.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    args.push(&s[start..]);
+    args.retain(|arg| !arg.is_empty());
+    args
+}
+
+/// An absolute or relative (`+`, `-`, or `*`) amount for an adjuster
+struct Amount {
+    op: Option<char>,
+    value: f32,
+}
+
+impl Amount {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (op, value) = match s.strip_prefix(['+', '-', '*']) {
+            Some(value) => (s.chars().next(), value),
+            None => (None, s),
+        };
+        let value = percentage(value.trim())?;
+        Some(Self { op, value })
+    }
+
+    fn apply(&self, current: f32) -> f32 {
+        let new = match self.op {
+            Some('+') => current + self.value,
+            Some('-') => current - self.value,
+            Some(_) => current * self.value,
+            None => self.value,
+        };
+        new.clamp(0.0, 1.0)
+    }
+}
+
+/// Parses either a percentage or a plain fraction into a fraction
+fn percentage(s: &str) -> Option<f32> {
+    match s.strip_suffix('%') {
+        Some(percent) => Some(percent.parse::<f32>().ok()? / 100.0),
+        None => s.parse().ok(),
+    }
+}
+
+/// Parses an optional trailing alpha argument
+fn alpha(rest: &[&str]) -> Option<f32> {
+    match rest {
+        [] => Some(1.0),
+        [alpha] => percentage(alpha),
+        _ => None,
+    }
+}
+
+/// Parses a hue in degrees into a fraction of a turn
+fn hue(s: &str) -> Option<f32> {
+    let degrees: f32 = s.strip_suffix("deg").unwrap_or(s).parse().ok()?;
+    Some((degrees / 360.0).rem_euclid(1.0))
+}
+
+fn to_hsl([r, g, b, _]: Rgba) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let delta = max - min;
+    if delta <= f32::EPSILON {
+        return [0.0, 0.0, l];
+    }
+    let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    [h / 6.0, s, l]
+}
+
+fn from_hsl([h, s, l]: [f32; 3], a: f32) -> Rgba {
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let sector = h.rem_euclid(1.0) * 6.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = l - chroma / 2.0;
+    [r + m, g + m, b + m, a]
+}
+
+/// WCAG relative luminance
+fn luminance([r, g, b, _]: Rgba) -> f32 {
+    let linear = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+fn contrast(a: Rgba, b: Rgba) -> f32 {
+    let (a, b) = (luminance(a) + 0.05, luminance(b) + 0.05);
+    a.max(b) / a.min(b)
+}
+
+/// Moves the color's lightness away from `other` until they reach the contrast `ratio`
+fn min_contrast(color: Rgba, other: Rgba, ratio: f32) -> Rgba {
+    let [h, s, l] = to_hsl(color);
+    let step = if luminance(color) >= luminance(other) {
+        0.01
+    } else {
+        -0.01
+    };
+    let mut adjusted = color;
+    for i in 0..=100 {
+        if contrast(adjusted, other) >= ratio {
+            break;
+        }
+        let lightness = (l + step * i as f32).clamp(0.0, 1.0);
+        adjusted = from_hsl([h, s, lightness], color[3]);
+    }
+    adjusted
+}
+
+fn named_color(name: &str) -> Option<Rgba> {
+    if name.eq_ignore_ascii_case("transparent") {
+        return Some([0.0; 4]);
+    }
+    let &(_, rgb) = NAMED_COLORS
+        .iter()
+        .find(|(named, _)| name.eq_ignore_ascii_case(named))?;
+    let [_, r, g, b] = rgb.to_be_bytes();
+    Some([r, g, b, 0xff].map(|c| f32::from(c) / 255.0))
+}
+
+/// The CSS named colors
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
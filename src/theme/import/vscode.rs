@@ -0,0 +1,509 @@
+//! Imports VS Code color themes
+//!
+//! VS Code themes are JSON files (comments and trailing commas allowed) where `tokenColors` holds
+//! TextMate style scope rules and `colors` holds the colors for the rest of the editor's UI. The
+//! token rules map directly to [`ThemeItem`]s while the UI colors that have an equivalent are
+//! mapped to [`ThemeSettings`]
+//!
+//! | VS Code color | [`ThemeSettings`] field |
+//! | :--- | :--- |
+//! | `editor.foreground` | `foreground` |
+//! | `editor.background` | `background` |
+//! | `editor.lineHighlightBackground` | `line_highlight` |
+//! | `editor.selectionBackground` | `selection` |
+//! | `editor.selectionForeground` | `selection_foreground` |
+//! | `editor.inactiveSelectionBackground` | `inactive_selection` |
+//! | `editor.findMatchHighlightBackground` | `find_highlight` |
+//! | `editorCursor.foreground` | `caret` |
+//! | `editorGutter.background` | `gutter` |
+//! | `editorLineNumber.foreground` | `gutter_foreground` |
+//! | `editorIndentGuide.background` | `guide` |
+//! | `editorIndentGuide.activeBackground` | `active_guide` |
+//!
+//! `semanticTokenColors` rules are mapped to the TextMate scopes that VS Code falls back to for
+//! each semantic token type. Rules that select modifiers or languages (e.g. `variable.readonly` or
+//! `*:rust`) have no equivalent, so they're skipped
+//!
+//! _Note: `include`d parent themes and `tokenColors` that point to a separate file aren't
+//! followed, but the rest of the theme is still imported_
+
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+
+use serde_derive::Deserialize;
+use syntect::highlighting::{
+    FontStyle, ScopeSelectors, StyleModifier, Theme, ThemeItem, ThemeSettings,
+};
+
+use super::common::{parse_hex_color, strip_jsonc, Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VscodeTheme {
+    name: Option<String>,
+    author: Option<String>,
+    #[serde(default)]
+    colors: BTreeMap<String, Option<String>>,
+    #[serde(default)]
+    token_colors: TokenColors,
+    #[serde(default)]
+    semantic_token_colors: BTreeMap<String, SemanticStyle>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenColors {
+    Rules(Vec<TokenColor>),
+    // A path to a separate `.tmTheme` or JSON file
+    Path(#[allow(dead_code)] String),
+}
+
+impl Default for TokenColors {
+    fn default() -> Self {
+        Self::Rules(Vec::new())
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenColor {
+    scope: Option<Scopes>,
+    #[serde(default)]
+    settings: TokenSettings,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scopes {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenSettings {
+    foreground: Option<String>,
+    background: Option<String>,
+    font_style: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SemanticStyle {
+    Foreground(String),
+    Settings(SemanticSettings),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SemanticSettings {
+    foreground: Option<String>,
+    font_style: Option<String>,
+    bold: Option<bool>,
+    italic: Option<bool>,
+    underline: Option<bool>,
+}
+
+/// The TextMate scopes that VS Code falls back to for each semantic token type
+const SEMANTIC_SCOPES: [(&str, &str); 23] = [
+    ("comment", "comment"),
+    ("string", "string"),
+    ("keyword", "keyword.control"),
+    ("number", "constant.numeric"),
+    ("regexp", "constant.regexp"),
+    ("operator", "keyword.operator"),
+    ("namespace", "entity.name.namespace"),
+    ("type", "entity.name.type, support.type"),
+    ("struct", "entity.name.type.struct"),
+    ("class", "entity.name.type.class, support.class"),
+    ("interface", "entity.name.type.interface"),
+    ("enum", "entity.name.type.enum"),
+    ("typeParameter", "entity.name.type.parameter"),
+    ("function", "entity.name.function, support.function"),
+    ("method", "entity.name.function.member, support.function"),
+    ("macro", "entity.name.function.preprocessor"),
+    ("variable", "variable.other.readonly, entity.name.variable"),
+    ("parameter", "variable.parameter"),
+    ("property", "variable.other.property"),
+    ("enumMember", "variable.other.enummember"),
+    ("event", "variable.other.event"),
+    ("decorator", "entity.name.decorator, entity.name.function"),
+    ("label", "entity.name.label"),
+];
+
+/// Parses the contents of a VS Code color theme
+///
+/// Colors that can't be parsed and rules with invalid scopes are skipped like VS Code does
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::import::vscode;
+///
+/// let theme = vscode::parse(r##"{
+///     "name": "Tiny",
+///     // JSONC comments are fine
+///     "colors": {
+///         "editor.background": "#1e1e1e",
+///         "editor.foreground": "#d4d4d4",
+///     },
+///     "tokenColors": [
+///         { "scope": ["comment", "string.quoted"], "settings": { "fontStyle": "italic" } },
+///     ],
+/// }"##).unwrap();
+/// assert_eq!(theme.name.as_deref(), Some("Tiny"));
+/// assert_eq!(theme.settings.background.unwrap().r, 0x1e);
+/// assert_eq!(theme.scopes.len(), 1);
+/// ```
+pub fn parse(s: &str) -> Result<Theme, Error> {
+    let vscode: VscodeTheme = serde_json::from_str(&strip_jsonc(s))?;
+    Ok(vscode.into())
+}
+
+/// Reads and parses a VS Code color theme file
+///
+/// See [`parse()`] for the details
+///
+/// # Example
+///
+/// ```no_run
+/// use two_face::theme::{extra, import::vscode, LazyThemeSet};
+/// use syntect::highlighting::ThemeSet;
+///
+/// // Drop the imported theme in next to the embedded ones
+/// let mut theme_set = ThemeSet::from(&extra());
+/// let theme = vscode::load_from_file("themes/tiny-color-theme.json")?;
+/// theme_set.themes.insert("Tiny".to_owned(), theme);
+/// let lazy = LazyThemeSet::from(&theme_set);
+/// # Ok::<_, two_face::theme::import::Error>(())
+/// ```
+pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Theme, Error> {
+    let contents = fs::read_to_string(path)?;
+    parse(&contents)
+}
+
+impl From<VscodeTheme> for Theme {
+    fn from(vscode: VscodeTheme) -> Self {
+        let VscodeTheme {
+            name,
+            author,
+            colors,
+            token_colors,
+            semantic_token_colors,
+        } = vscode;
+
+        let mut settings = ThemeSettings::default();
+        let mut scopes = Vec::new();
+        let token_colors = match token_colors {
+            TokenColors::Rules(rules) => rules,
+            TokenColors::Path(_) => Vec::new(),
+        };
+        for TokenColor {
+            scope,
+            settings: token,
+        } in token_colors
+        {
+            let style = token.into();
+            let Some(scope) = scope else {
+                // Old themes put the default colors in a rule without a scope like tmTheme does
+                let StyleModifier {
+                    foreground,
+                    background,
+                    ..
+                } = style;
+                settings.foreground = foreground.or(settings.foreground);
+                settings.background = background.or(settings.background);
+                continue;
+            };
+
+            let scope = match scope {
+                Scopes::One(scope) => scope,
+                Scopes::Many(scopes) => scopes.join(", "),
+            };
+            if scope.trim().is_empty() {
+                continue;
+            }
+            if let Ok(scope) = ScopeSelectors::from_str(&scope) {
+                scopes.push(ThemeItem { scope, style });
+            }
+        }
+        for (selector, style) in semantic_token_colors {
+            let Some((_, scope)) = SEMANTIC_SCOPES.iter().find(|(kind, _)| *kind == selector)
+            else {
+                continue;
+            };
+            let scope = ScopeSelectors::from_str(scope).expect("Valid scope selectors");
+            scopes.push(ThemeItem {
+                scope,
+                style: style.into(),
+            });
+        }
+
+        let fields = [
+            ("editor.foreground", &mut settings.foreground),
+            ("editor.background", &mut settings.background),
+            (
+                "editor.lineHighlightBackground",
+                &mut settings.line_highlight,
+            ),
+            ("editor.selectionBackground", &mut settings.selection),
+            (
+                "editor.selectionForeground",
+                &mut settings.selection_foreground,
+            ),
+            (
+                "editor.inactiveSelectionBackground",
+                &mut settings.inactive_selection,
+            ),
+            (
+                "editor.findMatchHighlightBackground",
+                &mut settings.find_highlight,
+            ),
+            ("editorCursor.foreground", &mut settings.caret),
+            ("editorGutter.background", &mut settings.gutter),
+            (
+                "editorLineNumber.foreground",
+                &mut settings.gutter_foreground,
+            ),
+            ("editorIndentGuide.background", &mut settings.guide),
+            (
+                "editorIndentGuide.activeBackground",
+                &mut settings.active_guide,
+            ),
+        ];
+        for (key, field) in fields {
+            let color = colors
+                .get(key)
+                .and_then(|color| parse_hex_color(color.as_deref()?));
+            if let Some(color) = color {
+                *field = Some(color);
+            }
+        }
+
+        Self {
+            name,
+            author,
+            settings,
+            scopes,
+        }
+    }
+}
+
+impl From<TokenSettings> for StyleModifier {
+    fn from(settings: TokenSettings) -> Self {
+        let color = |color: Option<String>| parse_hex_color(&color?);
+        let font_style = settings.font_style.map(|font_style| {
+            font_style
+                .split_whitespace()
+                .fold(FontStyle::empty(), |acc, word| match word {
+                    "bold" => acc | FontStyle::BOLD,
+                    "italic" => acc | FontStyle::ITALIC,
+                    "underline" => acc | FontStyle::UNDERLINE,
+                    // `strikethrough` and friends have no equivalent
+                    _ => acc,
+                })
+        });
+        Self {
+            foreground: color(settings.foreground),
+            background: color(settings.background),
+            font_style,
+        }
+    }
+}
+
+impl From<SemanticStyle> for StyleModifier {
+    fn from(style: SemanticStyle) -> Self {
+        let settings = match style {
+            SemanticStyle::Foreground(foreground) => {
+                return TokenSettings {
+                    foreground: Some(foreground),
+                    ..TokenSettings::default()
+                }
+                .into();
+            }
+            SemanticStyle::Settings(settings) => settings,
+        };
+        let mut style: Self = TokenSettings {
+            foreground: settings.foreground,
+            background: None,
+            font_style: settings.font_style,
+        }
+        .into();
+        // The individual flags take priority over `fontStyle`
+        for (flag, enabled) in [
+            (FontStyle::BOLD, settings.bold),
+            (FontStyle::ITALIC, settings.italic),
+            (FontStyle::UNDERLINE, settings.underline),
+        ] {
+            if let Some(enabled) = enabled {
+                let font_style = style.font_style.get_or_insert_with(FontStyle::empty);
+                font_style.set(flag, enabled);
+            }
+        }
+        style
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::theme::to_tmtheme;
+
+    const THEME: &str = r##"// Comments before the theme
+{
+    "$schema": "vscode://schemas/color-theme",
+    "name": "Tiny Dark",
+    "type": "dark",
+    "colors": {
+        "editor.background": "#1E1E1E",
+        "editor.foreground": "#d4d4d4",
+        "editor.lineHighlightBackground": "#ffffff0f",
+        "editorGutter.background": "#1e1e1e",
+        "editorLineNumber.foreground": "#858585",
+        "editorCursor.foreground": "#aeafad",
+        "activityBar.background": "#333333", // No equivalent
+        "editor.selectionBackground": null,
+    },
+    "tokenColors": [
+        {
+            "settings": { "foreground": "#ff0000", "background": "#00ff00" }
+        },
+        {
+            "name": "Comments",
+            "scope": "comment, punctuation.definition.comment",
+            "settings": { "foreground": "#6A9955", "fontStyle": "italic" }
+        },
+        {
+            "scope": ["string", "meta.embedded - source"],
+            "settings": { "foreground": "#ce9178", "fontStyle": "" }
+        },
+        {
+            "scope": "markup.strikethrough",
+            "settings": { "fontStyle": "bold strikethrough" }
+        },
+        {
+            "scope": "invalid",
+            "settings": { "foreground": "not a color", "background": "#f00" }
+        },
+    ],
+    "semanticTokenColors": { "variable": "#9cdcfe" }
+}"##;
+
+    #[test]
+    fn tiny_theme() {
+        let theme = parse(THEME).unwrap();
+        insta::assert_snapshot!(to_tmtheme(&theme), @r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+        <plist version="1.0">
+        <dict>
+        	<key>name</key>
+        	<string>Tiny Dark</string>
+        	<key>settings</key>
+        	<array>
+        		<dict>
+        			<key>settings</key>
+        			<dict>
+        				<key>foreground</key>
+        				<string>#D4D4D4</string>
+        				<key>background</key>
+        				<string>#1E1E1E</string>
+        				<key>caret</key>
+        				<string>#AEAFAD</string>
+        				<key>lineHighlight</key>
+        				<string>#FFFFFF0F</string>
+        				<key>gutter</key>
+        				<string>#1E1E1E</string>
+        				<key>gutterForeground</key>
+        				<string>#858585</string>
+        			</dict>
+        		</dict>
+        		<dict>
+        			<key>scope</key>
+        			<string>comment, punctuation.definition.comment</string>
+        			<key>settings</key>
+        			<dict>
+        				<key>foreground</key>
+        				<string>#6A9955</string>
+        				<key>fontStyle</key>
+        				<string>italic</string>
+        			</dict>
+        		</dict>
+        		<dict>
+        			<key>scope</key>
+        			<string>string, meta.embedded - source</string>
+        			<key>settings</key>
+        			<dict>
+        				<key>foreground</key>
+        				<string>#CE9178</string>
+        				<key>fontStyle</key>
+        				<string></string>
+        			</dict>
+        		</dict>
+        		<dict>
+        			<key>scope</key>
+        			<string>markup.strikethrough</string>
+        			<key>settings</key>
+        			<dict>
+        				<key>fontStyle</key>
+        				<string>bold</string>
+        			</dict>
+        		</dict>
+        		<dict>
+        			<key>scope</key>
+        			<string>invalid</string>
+        			<key>settings</key>
+        			<dict>
+        				<key>background</key>
+        				<string>#FF0000</string>
+        			</dict>
+        		</dict>
+        		<dict>
+        			<key>scope</key>
+        			<string>variable.other.readonly, entity.name.variable</string>
+        			<key>settings</key>
+        			<dict>
+        				<key>foreground</key>
+        				<string>#9CDCFE</string>
+        			</dict>
+        		</dict>
+        	</array>
+        </dict>
+        </plist>
+        "#);
+    }
+
+    #[test]
+    fn separate_token_colors() {
+        let theme = parse(
+            r##"{
+                "tokenColors": "./tiny.tmTheme",
+                "colors": { "editor.background": "#123456" },
+                "semanticTokenColors": {
+                    "function": { "foreground": "#dcdcaa", "fontStyle": "italic", "bold": true },
+                    "variable.readonly": "#4fc1ff",
+                    "*.declaration": { "bold": true }
+                }
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(theme.settings.background.unwrap().b, 0x56);
+        let [function] = &theme.scopes[..] else {
+            panic!("{:?}", theme.scopes);
+        };
+        assert_eq!(
+            function.scope,
+            ScopeSelectors::from_str("entity.name.function, support.function").unwrap()
+        );
+        assert_eq!(function.style.foreground.unwrap().r, 0xdc);
+        assert_eq!(
+            function.style.font_style,
+            Some(FontStyle::BOLD | FontStyle::ITALIC)
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let err = parse("{ \"name\": \"Missing brace\"").unwrap_err();
+        insta::assert_snapshot!(err, @"invalid theme: EOF while parsing an object at line 1 column 25");
+    }
+}
@@ -0,0 +1,157 @@
+//! Lazily loading `.tmTheme` files at runtime
+
+use std::{
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use syntect::highlighting::{Theme, ThemeSet};
+
+use super::core_types::{LazyTheme, LazyThemeSet};
+
+impl LazyThemeSet {
+    /// Loads all of the `.tmTheme` files from `folder` (including nested folders) without parsing
+    /// them
+    ///
+    /// Themes are named after their file stems, and each file only gets parsed once it's accessed
+    /// through [`LazyThemeSet::get()`]
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use two_face::theme::{extra, LazyThemeSet};
+    ///
+    /// // Combine the embedded themes with the user's own
+    /// let mut theme_set = LazyThemeSet::from(extra());
+    /// theme_set.extend(LazyThemeSet::load_from_folder("themes")?);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn load_from_folder<P: AsRef<Path>>(folder: P) -> io::Result<Self> {
+        let mut theme_set = Self {
+            themes: Default::default(),
+        };
+        for path in tm_theme_paths(folder.as_ref())? {
+            if let Some(name) = theme_name(&path) {
+                theme_set.insert_lazy_from_bytes(name, fs::read(&path)?);
+            }
+        }
+        Ok(theme_set)
+    }
+
+    /// Adds the contents of a `.tmTheme` file to the set without parsing it
+    ///
+    /// The theme gets parsed when it's first accessed through [`LazyThemeSet::get()`] which
+    /// returns `None` if it's invalid
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{extra, to_tmtheme, EmbeddedThemeName, LazyThemeSet};
+    ///
+    /// let embedded = extra();
+    /// let nord = to_tmtheme(embedded.get(EmbeddedThemeName::Nord));
+    /// let mut theme_set = LazyThemeSet::from(embedded);
+    /// theme_set.insert_lazy_from_bytes("Nord Again", nord.into_bytes());
+    /// theme_set.insert_lazy_from_bytes("Broken", b"not a theme".to_vec());
+    /// assert_eq!(theme_set.get("Nord Again"), theme_set.get("Nord"));
+    /// assert!(theme_set.get("Broken").is_none());
+    /// ```
+    pub fn insert_lazy_from_bytes(&mut self, name: impl Into<String>, tm_theme: Vec<u8>) {
+        let lazy = LazyTheme {
+            serialized: tm_theme,
+            parse: Some(parse_tm_theme),
+            deserialized: OnceLock::new(),
+        };
+        self.themes.insert(name.into(), lazy);
+    }
+}
+
+/// Finds all of the `.tmTheme` files in `folder` including nested folders
+///
+/// Symlinks to folders aren't followed so that a link back to a parent can't recurse forever
+pub(crate) fn tm_theme_paths(folder: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            paths.extend(tm_theme_paths(&path)?);
+        } else if path.extension() == Some(OsStr::new("tmTheme")) {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Themes loaded from files are named after their file stems
+pub(crate) fn theme_name(path: &Path) -> Option<String> {
+    path.file_stem()
+        .and_then(OsStr::to_str)
+        .map(ToOwned::to_owned)
+}
+
+fn parse_tm_theme(tm_theme: &[u8]) -> Option<Theme> {
+    ThemeSet::load_from_reader(&mut io::Cursor::new(tm_theme)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::theme::{extra, to_tmtheme, EmbeddedThemeName};
+
+    #[test]
+    fn load_from_folder() {
+        let embedded = extra();
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let nested = dir.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        for (path, name) in [
+            (dir.join("Nord.tmTheme"), EmbeddedThemeName::Nord),
+            (nested.join("Zen.tmTheme"), EmbeddedThemeName::Zenburn),
+            (dir.join("ignored.json"), EmbeddedThemeName::Dracula),
+        ] {
+            fs::write(path, to_tmtheme(embedded.get(name))).unwrap();
+        }
+        fs::write(dir.join("Broken.tmTheme"), "<plist>").unwrap();
+
+        let theme_set = LazyThemeSet::load_from_folder(dir).unwrap();
+        let names: Vec<_> = theme_set.theme_names().collect();
+        assert_eq!(names, ["Broken", "Nord", "Zen"]);
+        assert_eq!(
+            theme_set.get("Zen"),
+            Some(embedded.get(EmbeddedThemeName::Zenburn))
+        );
+        assert!(theme_set.get("Broken").is_none());
+
+        // Serializing leaves out the source format, so the themes get dumped as they're loaded and
+        // invalid themes get left out
+        let dumped = syntect::dumps::dump_binary(&theme_set);
+        let loaded: LazyThemeSet = syntect::dumps::from_binary(&dumped);
+        let names: Vec<_> = loaded.theme_names().collect();
+        assert_eq!(names, ["Nord", "Zen"]);
+        assert_eq!(
+            loaded.get("Nord"),
+            Some(embedded.get(EmbeddedThemeName::Nord))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loop() {
+        let embedded = extra();
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let nested = dir.join("nested");
+        fs::create_dir(&nested).unwrap();
+        let nord = to_tmtheme(embedded.get(EmbeddedThemeName::Nord));
+        fs::write(nested.join("Nord.tmTheme"), nord).unwrap();
+        std::os::unix::fs::symlink(dir, nested.join("parent")).unwrap();
+
+        let paths = tm_theme_paths(dir).unwrap();
+        assert_eq!(paths, [nested.join("Nord.tmTheme")]);
+    }
+}
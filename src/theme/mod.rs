@@ -7,13 +7,33 @@
 //! _Note: For visual examples of all of the embedded themes look at the docs for
 //! [`EmbeddedThemeName`]_
 
+pub mod base16;
+mod builder;
 mod core_types;
+pub(crate) mod css;
+pub mod export;
+#[cfg(feature = "import")]
+pub mod import;
+#[cfg(feature = "plist-load")]
+mod load;
+mod registry;
+mod terminal_scheme;
+mod tmtheme;
+mod transform;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 use std::{fmt, ops::Index};
 
-pub use core_types::LazyThemeSet;
+pub use builder::ThemeBuilder;
+pub use core_types::{IntoIter, Iter, LazyThemeSet, MemoryUsage, Names};
+pub use css::{css, css_all_themes, css_for_theme, dual_css, dual_css_for_themes};
+pub use registry::{ThemeId, ThemeRegistry};
+pub use terminal_scheme::TerminalScheme;
+pub use tmtheme::to_tmtheme;
+pub use transform::{downgrade_font_styles, quantize, without_background};
 
-use syntect::highlighting::{Theme, ThemeSet};
+use syntect::highlighting::{Color, Theme, ThemeSet};
 
 /// Returns an [`EmbeddedLazyThemeSet`] with more popular theme definitions
 ///
@@ -39,6 +59,7 @@ pub fn extra() -> EmbeddedLazyThemeSet {
 }
 
 /// A [`LazyThemeSet`] where we know all of the themes that are included
+#[derive(Clone, Debug)]
 pub struct EmbeddedLazyThemeSet(LazyThemeSet);
 
 impl EmbeddedLazyThemeSet {
@@ -135,6 +156,37 @@ impl Index<EmbeddedThemeName> for EmbeddedLazyThemeSet {
     }
 }
 
+/// Whether a theme is meant to be displayed on a light or dark background
+///
+/// This is mostly useful for picking a theme automatically to match the user's terminal or
+/// system preferences
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Appearance {
+    /// Dark text on a light background
+    Light,
+    /// Light text on a dark background
+    Dark,
+}
+
+impl Appearance {
+    /// Classifies a background color as being either light or dark
+    ///
+    /// ```
+    /// use two_face::theme::Appearance;
+    /// use syntect::highlighting::Color;
+    ///
+    /// assert_eq!(Appearance::from_background(Color::WHITE), Appearance::Light);
+    /// assert_eq!(Appearance::from_background(Color::BLACK), Appearance::Dark);
+    /// ```
+    pub fn from_background(color: Color) -> Self {
+        if crate::color::lightness(color) > 50.0 {
+            Self::Light
+        } else {
+            Self::Dark
+        }
+    }
+}
+
 // NOTE: doc comment HTML is copied from the tests/docs_watchdog/theme.rs tests
 /// An enum that represents all themes included in [`EmbeddedLazyThemeSet`]
 ///
@@ -155,11 +207,11 @@ impl Index<EmbeddedThemeName> for EmbeddedLazyThemeSet {
 pub enum EmbeddedThemeName {
     /// ANSI
     ///
-    /// _Doesn't display as HTML well_
+    /// _Uses the terminal's palette. See [`crate::html::AnsiPalette`] for displaying it as HTML_
     Ansi,
     /// Base16
     ///
-    /// _Doesn't display as HTML well_
+    /// _Uses the terminal's palette. See [`crate::html::AnsiPalette`] for displaying it as HTML_
     Base16,
     /// Base16 Eighties Dark
     ///
@@ -211,7 +263,7 @@ pub enum EmbeddedThemeName {
     Base16OceanLight,
     /// Base16 256
     ///
-    /// _Doesn't display as HTML well_
+    /// _Uses the terminal's palette. See [`crate::html::AnsiPalette`] for displaying it as HTML_
     Base16_256,
     /// Catppuccin Frappe
     ///
@@ -569,6 +621,100 @@ impl EmbeddedThemeName {
             Self::Zenburn => "zenburn",
         }
     }
+
+    /// A kebab-case identifier for each embedded theme
+    ///
+    /// Handy for places where the display names don't fit like CSS selectors or file names
+    ///
+    /// ```
+    /// use two_face::theme::EmbeddedThemeName;
+    ///
+    /// assert_eq!(EmbeddedThemeName::Nord.as_slug(), "nord");
+    /// assert_eq!(EmbeddedThemeName::SolarizedDark.as_slug(), "solarized-dark");
+    /// ```
+    pub fn as_slug(self) -> &'static str {
+        match self {
+            Self::Ansi => "ansi",
+            Self::Base16 => "base16",
+            Self::Base16EightiesDark => "base16-eighties-dark",
+            Self::Base16MochaDark => "base16-mocha-dark",
+            Self::Base16OceanDark => "base16-ocean-dark",
+            Self::Base16OceanLight => "base16-ocean-light",
+            Self::Base16_256 => "base16-256",
+            Self::CatppuccinFrappe => "catppuccin-frappe",
+            Self::CatppuccinLatte => "catppuccin-latte",
+            Self::CatppuccinMacchiato => "catppuccin-macchiato",
+            Self::CatppuccinMocha => "catppuccin-mocha",
+            Self::ColdarkCold => "coldark-cold",
+            Self::ColdarkDark => "coldark-dark",
+            Self::DarkNeon => "dark-neon",
+            Self::Dracula => "dracula",
+            Self::Github => "github",
+            Self::GruvboxDark => "gruvbox-dark",
+            Self::GruvboxLight => "gruvbox-light",
+            Self::InspiredGithub => "inspired-github",
+            Self::Leet => "leet",
+            Self::MonokaiExtended => "monokai-extended",
+            Self::MonokaiExtendedBright => "monokai-extended-bright",
+            Self::MonokaiExtendedLight => "monokai-extended-light",
+            Self::MonokaiExtendedOrigin => "monokai-extended-origin",
+            Self::Nord => "nord",
+            Self::OneHalfDark => "one-half-dark",
+            Self::OneHalfLight => "one-half-light",
+            Self::SolarizedDark => "solarized-dark",
+            Self::SolarizedLight => "solarized-light",
+            Self::SublimeSnazzy => "sublime-snazzy",
+            Self::TwoDark => "two-dark",
+            Self::Zenburn => "zenburn",
+        }
+    }
+
+    /// Whether the theme is meant for a light or dark background
+    ///
+    /// Returns [`None`] for the themes that defer to the terminal's own palette (`Ansi`, `Base16`,
+    /// and `Base16_256`) since they look right on either
+    ///
+    /// ```
+    /// use two_face::theme::{Appearance, EmbeddedThemeName};
+    ///
+    /// assert_eq!(EmbeddedThemeName::Nord.appearance(), Some(Appearance::Dark));
+    /// assert_eq!(EmbeddedThemeName::GruvboxLight.appearance(), Some(Appearance::Light));
+    /// assert_eq!(EmbeddedThemeName::Ansi.appearance(), None);
+    /// ```
+    pub fn appearance(self) -> Option<Appearance> {
+        match self {
+            Self::Ansi | Self::Base16 | Self::Base16_256 => None,
+            Self::Base16OceanLight
+            | Self::CatppuccinLatte
+            | Self::ColdarkCold
+            | Self::Github
+            | Self::GruvboxLight
+            | Self::InspiredGithub
+            | Self::MonokaiExtendedLight
+            | Self::OneHalfLight
+            | Self::SolarizedLight => Some(Appearance::Light),
+            Self::Base16EightiesDark
+            | Self::Base16MochaDark
+            | Self::Base16OceanDark
+            | Self::CatppuccinFrappe
+            | Self::CatppuccinMacchiato
+            | Self::CatppuccinMocha
+            | Self::ColdarkDark
+            | Self::DarkNeon
+            | Self::Dracula
+            | Self::GruvboxDark
+            | Self::Leet
+            | Self::MonokaiExtended
+            | Self::MonokaiExtendedBright
+            | Self::MonokaiExtendedOrigin
+            | Self::Nord
+            | Self::OneHalfDark
+            | Self::SolarizedDark
+            | Self::SublimeSnazzy
+            | Self::TwoDark
+            | Self::Zenburn => Some(Appearance::Dark),
+        }
+    }
 }
 
 impl fmt::Display for EmbeddedThemeName {
@@ -579,10 +725,12 @@ impl fmt::Display for EmbeddedThemeName {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeSet;
+    use std::{collections::BTreeSet, sync::OnceLock};
 
     use super::*;
 
+    use crate::terminal::ColorDepth;
+
     use strum::IntoEnumIterator;
 
     #[test]
@@ -598,6 +746,127 @@ mod tests {
         assert!(eq);
     }
 
+    #[test]
+    fn lazy_theme_set_edits() {
+        let embedded = extra();
+        let nord = embedded.get(EmbeddedThemeName::Nord).clone();
+        let mut theme_set = LazyThemeSet::from(&ThemeSet::new());
+        assert!(theme_set.is_empty());
+        theme_set.insert("Nord", Theme::default());
+        theme_set.extend(embedded);
+        assert_eq!(theme_set.len(), EmbeddedLazyThemeSet::theme_names().len());
+        assert_eq!(theme_set.get("Nord"), Some(&nord));
+
+        theme_set.insert("Custom", nord.clone());
+        assert!(theme_set.contains("Custom"));
+        assert_eq!(theme_set.get("Custom"), Some(&nord));
+        assert_eq!(theme_set.remove("Nord"), Some(nord.clone()));
+        assert_eq!(theme_set.remove("Nord"), None);
+        assert!(!theme_set.contains("Nord"));
+
+        // Themes inserted as is still get serialized as binary dumps
+        let dumped = syntect::dumps::dump_binary(&theme_set);
+        let loaded: LazyThemeSet = syntect::dumps::from_binary(&dumped);
+        assert_eq!(loaded.get("Custom"), Some(&nord));
+    }
+
+    #[test]
+    fn lazy_theme_set_iterators() {
+        let mut theme_set = LazyThemeSet::from(extra());
+        theme_set.insert("Broken", Theme::default());
+        theme_set.themes.get_mut("Broken").unwrap().deserialized = OnceLock::from(None);
+        let num_valid = EmbeddedLazyThemeSet::theme_names().len();
+
+        assert_eq!(theme_set.theme_names().len(), num_valid + 1);
+        assert_eq!(theme_set.theme_names().next_back(), Some("zenburn"));
+        let mut iter = theme_set.iter();
+        let (name, theme) = iter.next().unwrap();
+        assert_eq!(name, "1337");
+        assert_eq!(theme, &theme_set["1337"]);
+        assert!(theme_set.themes["zenburn"].deserialized.get().is_none());
+
+        // Broken themes are skipped
+        assert_eq!((&theme_set).into_iter().count(), num_valid);
+        let cloned = theme_set.clone();
+        let owned: Vec<_> = theme_set.into_iter().rev().collect();
+        assert_eq!(owned.len(), num_valid);
+        assert_eq!(owned[0].0, "zenburn");
+        assert_eq!(&owned[0].1, &cloned["zenburn"]);
+        let zenburn = &cloned.themes["zenburn"];
+        assert_eq!(
+            format!("{zenburn:?}"),
+            format!(
+                "LazyTheme {{ serialized_len: {}, loaded: true, .. }}",
+                zenburn.serialized.len()
+            )
+        );
+    }
+
+    #[test]
+    fn lazy_theme_set_eviction() {
+        let nord = extra()[EmbeddedThemeName::Nord].clone();
+        let mut theme_set = LazyThemeSet::from(extra());
+        theme_set.insert("Custom", nord.clone());
+        let loaded = |theme_set: &LazyThemeSet| {
+            theme_set
+                .theme_names()
+                .filter(|name| theme_set.memory_usage(name).unwrap().loaded.is_some())
+                .count()
+        };
+        assert_eq!(loaded(&theme_set), 1);
+        assert_eq!(theme_set.memory_usage("Custom").unwrap().serialized, 0);
+
+        theme_set.preload_all();
+        assert_eq!(loaded(&theme_set), theme_set.len());
+        assert!(theme_set.evict("Custom"));
+        assert!(theme_set.memory_usage("Custom").unwrap().serialized > 0);
+        assert_eq!(theme_set.get("Custom"), Some(&nord));
+        let usage = theme_set.memory_usage("Nord").unwrap();
+        assert!(usage.total() > usage.serialized);
+
+        theme_set.evict_all();
+        assert_eq!(loaded(&theme_set), 0);
+        assert_eq!(theme_set.get("Custom"), Some(&nord));
+        assert!(!theme_set.evict("Missing"));
+        assert_eq!(theme_set.memory_usage("Missing"), None);
+    }
+
+    #[test]
+    fn lazy_theme_set_dump_is_stable() {
+        let embedded = include_bytes!("../../generated/themes.bin");
+        let untouched: LazyThemeSet = syntect::dumps::from_uncompressed_data(embedded).unwrap();
+        let loaded: LazyThemeSet = syntect::dumps::from_uncompressed_data(embedded).unwrap();
+        loaded.preload_all();
+        // Loaded themes get dumped in their original form
+        assert!(syntect::dumps::dump_binary(&loaded) == syntect::dumps::dump_binary(&untouched));
+    }
+
+    #[test]
+    fn quantized_themes_only_use_the_palette() {
+        let theme_set = extra();
+        for depth in [ColorDepth::Ansi256, ColorDepth::Ansi16] {
+            for theme_name in EmbeddedThemeName::iter() {
+                let mut theme = quantize(theme_set.get(theme_name), depth);
+                let max_index = match depth {
+                    ColorDepth::Ansi16 => 15,
+                    _ => 255,
+                };
+                let in_palette = |color: Color| match color.a {
+                    0 => color.r <= max_index,
+                    1 => true,
+                    _ => false,
+                };
+
+                let settings = transform::settings_colors(&mut theme.settings);
+                assert!(settings.into_iter().flatten().all(|c| in_palette(*c)));
+                for item in theme.scopes {
+                    let colors = [item.style.foreground, item.style.background];
+                    assert!(colors.into_iter().flatten().all(in_palette));
+                }
+            }
+        }
+    }
+
     #[test]
     fn embedded_theme_is_exhaustive() {
         let theme_set = extra();
@@ -0,0 +1,223 @@
+use std::{fmt, ops::Index};
+
+use syntect::highlighting::Theme;
+
+use super::{extra, EmbeddedLazyThemeSet, EmbeddedThemeName, LazyThemeSet};
+
+/// Identifies a theme in a [`ThemeRegistry`]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThemeId {
+    /// One of the embedded themes (or a custom theme that shadows it)
+    Embedded(EmbeddedThemeName),
+    /// A custom theme
+    Custom(String),
+}
+
+impl ThemeId {
+    /// The theme's name
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use two_face::theme::{EmbeddedThemeName, ThemeId};
+    ///
+    /// assert_eq!(ThemeId::Embedded(EmbeddedThemeName::Nord).name(), "Nord");
+    /// assert_eq!(ThemeId::Custom("Mine".to_owned()).name(), "Mine");
+    /// ```
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Embedded(name) => name.as_name(),
+            Self::Custom(name) => name,
+        }
+    }
+}
+
+impl From<EmbeddedThemeName> for ThemeId {
+    fn from(name: EmbeddedThemeName) -> Self {
+        Self::Embedded(name)
+    }
+}
+
+impl fmt::Display for ThemeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The embedded themes along with any custom themes
+///
+/// Custom themes that share a name with an embedded theme shadow it, so you can swap in tweaked
+/// versions of the embedded themes while still looking them up infallibly by their
+/// [`EmbeddedThemeName`]
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::{EmbeddedThemeName, ThemeBuilder, ThemeId, ThemeRegistry};
+///
+/// let mut registry = ThemeRegistry::new();
+/// let nord = registry.get_embedded(EmbeddedThemeName::Nord);
+/// let muted = ThemeBuilder::new(nord).saturation(0.5).build();
+/// registry.insert("Nord Muted", muted);
+///
+/// // A single listing for theme pickers
+/// let ids: Vec<_> = registry.ids().collect();
+/// assert!(ids.contains(&ThemeId::Embedded(EmbeddedThemeName::Nord)));
+/// assert!(ids.contains(&ThemeId::Custom("Nord Muted".to_owned())));
+/// let id = registry.id("Nord Muted").unwrap();
+/// assert!(registry.get(&id).is_some());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ThemeRegistry {
+    embedded: EmbeddedLazyThemeSet,
+    custom: LazyThemeSet,
+}
+
+impl ThemeRegistry {
+    /// A registry with just the [`extra()`] themes
+    pub fn new() -> Self {
+        extra().into()
+    }
+
+    /// Adds a custom theme, replacing any custom theme with the same name
+    ///
+    /// Using the name of an embedded theme shadows that theme
+    pub fn insert(&mut self, name: impl Into<String>, theme: Theme) {
+        self.custom.insert(name, theme);
+    }
+
+    /// Adds all of the themes from `themes` as custom themes without loading them
+    pub fn extend(&mut self, themes: impl Into<LazyThemeSet>) {
+        self.custom.extend(themes);
+    }
+
+    /// Removes a custom theme, returning it if it was included
+    ///
+    /// Removing a custom theme that shadows an embedded theme makes the embedded theme visible
+    /// again
+    pub fn remove(&mut self, name: &str) -> Option<Theme> {
+        self.custom.remove(name)
+    }
+
+    /// Gets a theme
+    ///
+    /// A custom ID with an embedded theme's name gets the same theme as [`ThemeId::Embedded`].
+    /// Returns `None` for custom themes that aren't included or fail to load
+    pub fn get(&self, id: &ThemeId) -> Option<&Theme> {
+        match id {
+            ThemeId::Embedded(name) => Some(self.get_embedded(*name)),
+            ThemeId::Custom(name) => match embedded_name(name) {
+                Some(embedded) => Some(self.get_embedded(embedded)),
+                None => self.custom.get(name),
+            },
+        }
+    }
+
+    /// Gets an embedded theme or the custom theme that shadows it
+    ///
+    /// Falls back to the embedded theme if the shadowing theme fails to load
+    pub fn get_embedded(&self, name: EmbeddedThemeName) -> &Theme {
+        self.custom
+            .get(name.as_name())
+            .unwrap_or_else(|| self.embedded.get(name))
+    }
+
+    /// Looks up the ID for a theme name like the one from [`ThemeId::name()`]
+    pub fn id(&self, name: &str) -> Option<ThemeId> {
+        match embedded_name(name) {
+            Some(embedded) => Some(ThemeId::Embedded(embedded)),
+            None if self.custom.contains(name) => Some(ThemeId::Custom(name.to_owned())),
+            None => None,
+        }
+    }
+
+    /// Lists every theme in the registry
+    ///
+    /// All of the embedded themes come first followed by the custom themes that don't shadow an
+    /// embedded theme
+    pub fn ids(&self) -> impl Iterator<Item = ThemeId> + '_ {
+        let embedded = EmbeddedLazyThemeSet::theme_names()
+            .iter()
+            .copied()
+            .map(ThemeId::Embedded);
+        let custom = self
+            .custom
+            .theme_names()
+            .filter(|&name| embedded_name(name).is_none())
+            .map(|name| ThemeId::Custom(name.to_owned()));
+        embedded.chain(custom)
+    }
+
+    /// The custom themes
+    pub fn custom(&self) -> &LazyThemeSet {
+        &self.custom
+    }
+}
+
+fn embedded_name(name: &str) -> Option<EmbeddedThemeName> {
+    EmbeddedLazyThemeSet::theme_names()
+        .iter()
+        .copied()
+        .find(|embedded| embedded.as_name() == name)
+}
+
+impl Default for ThemeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<EmbeddedLazyThemeSet> for ThemeRegistry {
+    fn from(embedded: EmbeddedLazyThemeSet) -> Self {
+        Self {
+            embedded,
+            custom: LazyThemeSet {
+                themes: Default::default(),
+            },
+        }
+    }
+}
+
+impl Index<EmbeddedThemeName> for ThemeRegistry {
+    type Output = Theme;
+
+    fn index(&self, name: EmbeddedThemeName) -> &Self::Output {
+        self.get_embedded(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shadowing() {
+        let mut registry = ThemeRegistry::new();
+        let nord = registry[EmbeddedThemeName::Nord].clone();
+        let dracula = registry[EmbeddedThemeName::Dracula].clone();
+        let num_embedded = EmbeddedLazyThemeSet::theme_names().len();
+        let custom_nord = ThemeId::Custom("Nord".to_owned());
+        assert_eq!(registry.get(&custom_nord), Some(&nord));
+
+        registry.insert("Nord", dracula.clone());
+        registry.insert("Custom", nord.clone());
+        assert_eq!(registry[EmbeddedThemeName::Nord], dracula);
+        assert_eq!(registry.get(&custom_nord), Some(&dracula));
+        let id = ThemeId::Custom("Custom".to_owned());
+        assert_eq!(registry.get(&id), Some(&nord));
+        assert_eq!(registry.id("Custom"), Some(id));
+        assert_eq!(
+            registry.id("Nord"),
+            Some(ThemeId::Embedded(EmbeddedThemeName::Nord))
+        );
+        assert_eq!(registry.id("Missing"), None);
+
+        // Shadowed themes only get listed once
+        let ids: Vec<_> = registry.ids().collect();
+        assert_eq!(ids.len(), num_embedded + 1);
+        assert_eq!(ids.last().unwrap().name(), "Custom");
+
+        assert_eq!(registry.remove("Nord"), Some(dracula));
+        assert_eq!(registry[EmbeddedThemeName::Nord], nord);
+    }
+}
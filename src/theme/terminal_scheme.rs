@@ -0,0 +1,321 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use syntect::highlighting::{Color, Theme};
+
+use super::export::push_json_string;
+use crate::{color, html::AnsiPalette};
+
+// The OKLCh hues of the sRGB primaries and secondaries
+const HUES: [(usize, f32); 6] = [
+    (1, 29.2),
+    (2, 142.5),
+    (3, 109.8),
+    (4, 264.1),
+    (5, 328.4),
+    (6, 194.8),
+];
+const MIN_CHROMA: f32 = 0.05;
+const MAX_HUE_DISTANCE: f32 = 35.0;
+const NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// A terminal color scheme derived from a theme
+///
+/// The 16 palette colors are picked from the theme's scope colors by matching hues against the
+/// usual ANSI colors, falling back to xterm's colors when a theme has nothing close. Bright colors
+/// are lightened versions of the normal ones. Everything is derived deterministically, so the same
+/// theme always produces the same scheme
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::{extra, EmbeddedThemeName, TerminalScheme};
+///
+/// let theme_set = extra();
+/// let scheme = TerminalScheme::from_theme(&theme_set[EmbeddedThemeName::Dracula]);
+/// let kitty_conf = scheme.kitty();
+/// assert!(kitty_conf.contains("\nbackground #282a36\n"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TerminalScheme {
+    /// The scheme's name
+    pub name: String,
+    /// The default text color
+    pub foreground: Color,
+    /// The default background color
+    pub background: Color,
+    /// The cursor's color
+    pub cursor: Color,
+    /// The color of text under the cursor
+    pub cursor_text: Color,
+    /// The background color of selected text
+    pub selection: Color,
+    /// The 8 normal colors followed by the 8 bright colors
+    pub palette: [Color; 16],
+}
+
+impl TerminalScheme {
+    /// Derives a scheme from `theme`
+    ///
+    /// Palette colors in the theme are resolved with [`AnsiPalette::XTERM`] and the terminal's
+    /// default colors fall back to xterm's black and white
+    pub fn from_theme(theme: &Theme) -> Self {
+        let xterm = AnsiPalette::XTERM;
+        let settings = &theme.settings;
+        let raw_background = settings.background;
+        let resolve =
+            |color: Option<Color>| color.and_then(|color| xterm.resolve(color, raw_background));
+
+        let background = resolve(raw_background).unwrap_or(xterm.color(0));
+        let foreground = resolve(settings.foreground).unwrap_or(xterm.color(7));
+        let cursor = resolve(settings.caret).unwrap_or(foreground);
+        let selection =
+            resolve(settings.selection).unwrap_or_else(|| mix(background, foreground, 0x40));
+
+        let dark = color::lightness(background) < 50.0;
+        let white = color::rgb(0xff, 0xff, 0xff);
+        let mut palette = [background; 16];
+        if dark {
+            palette[0] = mix(background, foreground, 0x26);
+            palette[8] = mix(background, foreground, 0x66);
+            palette[7] = foreground;
+            palette[15] = mix(foreground, white, 0x55);
+        } else {
+            palette[0] = foreground;
+            palette[8] = mix(foreground, background, 0x66);
+            palette[7] = mix(background, foreground, 0x26);
+            palette[15] = background;
+        }
+
+        let candidates: BTreeSet<_> = theme
+            .scopes
+            .iter()
+            .filter_map(|item| resolve(item.style.foreground))
+            .map(|Color { r, g, b, .. }| (r, g, b))
+            .collect();
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .map(|(r, g, b)| {
+                let color = color::rgb(r, g, b);
+                let [_, a, b] = color::oklab(color);
+                let hue = b.atan2(a).to_degrees().rem_euclid(360.0);
+                (color, a.hypot(b), hue)
+            })
+            .filter(|&(_, chroma, _)| chroma >= MIN_CHROMA)
+            .collect();
+        // Each candidate only competes for the slot with the closest hue
+        let hue_distance = |hue: f32, target: f32| {
+            let distance = (hue - target).abs();
+            distance.min(360.0 - distance)
+        };
+        let nearest_slot = |hue: f32| {
+            HUES.iter()
+                .min_by(|a, b| hue_distance(hue, a.1).total_cmp(&hue_distance(hue, b.1)))
+                .map(|&(index, _)| index)
+        };
+        for (index, target) in HUES {
+            let closest = candidates
+                .iter()
+                .filter(|&&(_, _, hue)| nearest_slot(hue) == Some(index))
+                .map(|&(color, chroma, hue)| (color, chroma, hue_distance(hue, target)))
+                .filter(|&(_, _, distance)| distance <= MAX_HUE_DISTANCE)
+                // The candidates are already sorted, so ties are broken deterministically
+                .min_by(|a, b| a.2.total_cmp(&b.2).then(b.1.total_cmp(&a.1)));
+            palette[index] = match closest {
+                Some((color, _, _)) => color,
+                None => mix(background, xterm.color(index as u8), 0xcc),
+            };
+            palette[index + 8] = mix(palette[index], white, 0x40);
+        }
+
+        Self {
+            name: theme.name.clone().unwrap_or_else(|| "Unnamed".to_owned()),
+            foreground,
+            background,
+            cursor,
+            cursor_text: background,
+            selection,
+            palette,
+        }
+    }
+
+    /// Generates the `colors` section for an [Alacritty](https://alacritty.org) TOML config
+    pub fn alacritty(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "# {}", self.comment_name()).expect("Infallible");
+        out.push_str("[colors.primary]\n");
+        writeln!(out, "foreground = \"{}\"", hex(self.foreground)).expect("Infallible");
+        writeln!(out, "background = \"{}\"", hex(self.background)).expect("Infallible");
+        out.push_str("\n[colors.cursor]\n");
+        writeln!(out, "text = \"{}\"", hex(self.cursor_text)).expect("Infallible");
+        writeln!(out, "cursor = \"{}\"", hex(self.cursor)).expect("Infallible");
+        out.push_str("\n[colors.selection]\n");
+        out.push_str("text = \"CellForeground\"\n");
+        writeln!(out, "background = \"{}\"", hex(self.selection)).expect("Infallible");
+        for (section, colors) in [
+            ("normal", &self.palette[..8]),
+            ("bright", &self.palette[8..]),
+        ] {
+            writeln!(out, "\n[colors.{section}]").expect("Infallible");
+            for (name, &color) in NAMES.iter().zip(colors) {
+                writeln!(out, "{name} = \"{}\"", hex(color)).expect("Infallible");
+            }
+        }
+        out
+    }
+
+    /// Generates a [kitty](https://sw.kovidgoyal.net/kitty/) color config
+    pub fn kitty(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "# {}", self.comment_name()).expect("Infallible");
+        for (key, color) in [
+            ("foreground", self.foreground),
+            ("background", self.background),
+            ("cursor", self.cursor),
+            ("cursor_text_color", self.cursor_text),
+            ("selection_background", self.selection),
+        ] {
+            writeln!(out, "{key} {}", hex(color)).expect("Infallible");
+        }
+        out.push_str("selection_foreground none\n");
+        for (i, &color) in self.palette.iter().enumerate() {
+            writeln!(out, "color{i} {}", hex(color)).expect("Infallible");
+        }
+        out
+    }
+
+    /// Generates an entry for the `schemes` list in a Windows Terminal `settings.json`
+    pub fn windows_terminal(&self) -> String {
+        const WT_NAMES: [&str; 8] = [
+            "black", "red", "green", "yellow", "blue", "purple", "cyan", "white",
+        ];
+
+        let mut out = String::from("{\n    \"name\": ");
+        push_json_string(&mut out, &self.name);
+        out.push_str(",\n");
+        let mut entries = vec![
+            ("foreground".to_owned(), self.foreground),
+            ("background".to_owned(), self.background),
+            ("cursorColor".to_owned(), self.cursor),
+            ("selectionBackground".to_owned(), self.selection),
+        ];
+        for (i, name) in WT_NAMES.iter().enumerate() {
+            entries.push(((*name).to_owned(), self.palette[i]));
+        }
+        for (i, name) in WT_NAMES.iter().enumerate() {
+            let bright = format!("bright{}{}", name[..1].to_uppercase(), &name[1..]);
+            entries.push((bright, self.palette[i + 8]));
+        }
+        let last = entries.len() - 1;
+        for (i, (key, color)) in entries.into_iter().enumerate() {
+            let separator = if i == last { "" } else { "," };
+            writeln!(out, "    \"{key}\": \"{}\"{separator}", hex(color)).expect("Infallible");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Generates the color sections for a [foot](https://codeberg.org/dnkl/foot) `foot.ini`
+    pub fn foot(&self) -> String {
+        let bare = |color| hex(color)[1..].to_owned();
+        let mut out = String::new();
+        writeln!(out, "# {}", self.comment_name()).expect("Infallible");
+        out.push_str("[cursor]\n");
+        writeln!(
+            out,
+            "color={} {}",
+            bare(self.cursor_text),
+            bare(self.cursor)
+        )
+        .expect("Infallible");
+        out.push_str("\n[colors]\n");
+        writeln!(out, "foreground={}", bare(self.foreground)).expect("Infallible");
+        writeln!(out, "background={}", bare(self.background)).expect("Infallible");
+        writeln!(out, "selection-background={}", bare(self.selection)).expect("Infallible");
+        for (i, &color) in self.palette[..8].iter().enumerate() {
+            writeln!(out, "regular{i}={}", bare(color)).expect("Infallible");
+        }
+        for (i, &color) in self.palette[8..].iter().enumerate() {
+            writeln!(out, "bright{i}={}", bare(color)).expect("Infallible");
+        }
+        out
+    }
+
+    /// Generates X resources for terminals like xterm and urxvt
+    pub fn xresources(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "! {}", self.comment_name()).expect("Infallible");
+        writeln!(out, "*.foreground: {}", hex(self.foreground)).expect("Infallible");
+        writeln!(out, "*.background: {}", hex(self.background)).expect("Infallible");
+        writeln!(out, "*.cursorColor: {}", hex(self.cursor)).expect("Infallible");
+        for (i, &color) in self.palette.iter().enumerate() {
+            writeln!(out, "*.color{i}: {}", hex(color)).expect("Infallible");
+        }
+        out
+    }
+
+    /// The name with any line breaks removed, so that it can't escape a comment
+    fn comment_name(&self) -> String {
+        self.name.replace(['\r', '\n'], " ")
+    }
+}
+
+/// Mixes `amount / 255` of `to` into `from`
+fn mix(from: Color, to: Color, amount: u8) -> Color {
+    color::blend(Color { a: amount, ..to }, from)
+}
+
+fn hex(Color { r, g, b, .. }: Color) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use syntect::highlighting::{ThemeItem, ThemeSettings};
+
+    #[test]
+    fn picks_matching_hues() {
+        let theme = Theme {
+            name: Some("Tiny \"theme\"\nwith a newline".into()),
+            settings: ThemeSettings {
+                foreground: Some(color::rgb(0xee, 0xee, 0xee)),
+                background: Some(color::rgb(0x11, 0x11, 0x11)),
+                ..Default::default()
+            },
+            scopes: [(0xe0, 0x40, 0x40), (0x40, 0xc0, 0x40), (0x40, 0x80, 0xf0)]
+                .into_iter()
+                .map(|(r, g, b)| ThemeItem {
+                    scope: "comment".parse().unwrap(),
+                    style: syntect::highlighting::StyleModifier {
+                        foreground: Some(color::rgb(r, g, b)),
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let scheme = TerminalScheme::from_theme(&theme);
+        assert_eq!(scheme.palette[1], color::rgb(0xe0, 0x40, 0x40));
+        assert_eq!(scheme.palette[2], color::rgb(0x40, 0xc0, 0x40));
+        assert_eq!(scheme.palette[4], color::rgb(0x40, 0x80, 0xf0));
+        // Yellow has nothing close, so it falls back to xterm's
+        assert_eq!(
+            scheme.palette[3],
+            mix(scheme.background, color::rgb(0xcd, 0xcd, 0x00), 0xcc)
+        );
+        assert_eq!(
+            scheme.selection,
+            mix(scheme.background, scheme.foreground, 0x40)
+        );
+
+        assert!(scheme
+            .kitty()
+            .starts_with("# Tiny \"theme\" with a newline\n"));
+        assert!(scheme
+            .windows_terminal()
+            .starts_with("{\n    \"name\": \"Tiny \\\"theme\\\"\\nwith a newline\",\n"));
+    }
+}
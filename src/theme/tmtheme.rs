@@ -0,0 +1,275 @@
+use std::fmt::Write;
+
+use syntect::highlighting::{
+    Color, FontStyle, ScopeSelectors, StyleModifier, Theme, ThemeSettings, UnderlineOption,
+};
+
+use super::transform::selector_text;
+
+/// Serializes a theme to the `.tmTheme` plist format
+///
+/// Loading the output with [`syntect::highlighting::ThemeSet::load_from_reader()`] gives back an
+/// equal theme. The only exception is an underline option of [`UnderlineOption::None`] which
+/// syntect can't load, so it gets left out
+///
+/// # Example
+///
+/// ```
+/// use two_face::theme::{extra, to_tmtheme, EmbeddedThemeName};
+///
+/// let theme_set = extra();
+/// let tmtheme = to_tmtheme(&theme_set[EmbeddedThemeName::Nord]);
+/// assert!(tmtheme.contains("\t<key>name</key>\n\t<string>Nord</string>\n"));
+/// ```
+pub fn to_tmtheme(theme: &Theme) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+        \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+        <plist version=\"1.0\">\n\
+        <dict>\n",
+    );
+    if let Some(name) = &theme.name {
+        write_entry(&mut out, 1, "name", name);
+    }
+    if let Some(author) = &theme.author {
+        write_entry(&mut out, 1, "author", author);
+    }
+
+    out.push_str("\t<key>settings</key>\n\t<array>\n");
+    out.push_str("\t\t<dict>\n\t\t\t<key>settings</key>\n\t\t\t<dict>\n");
+    for (key, value) in settings_entries(&theme.settings) {
+        write_entry(&mut out, 4, key, &value);
+    }
+    out.push_str("\t\t\t</dict>\n\t\t</dict>\n");
+
+    for item in &theme.scopes {
+        out.push_str("\t\t<dict>\n");
+        write_entry(&mut out, 3, "scope", &selectors_text(&item.scope));
+        out.push_str("\t\t\t<key>settings</key>\n\t\t\t<dict>\n");
+        let StyleModifier {
+            foreground,
+            background,
+            font_style,
+        } = item.style;
+        if let Some(color) = foreground {
+            write_entry(&mut out, 4, "foreground", &color_text(color));
+        }
+        if let Some(color) = background {
+            write_entry(&mut out, 4, "background", &color_text(color));
+        }
+        if let Some(font_style) = font_style {
+            write_entry(&mut out, 4, "fontStyle", &font_style_text(font_style));
+        }
+        out.push_str("\t\t\t</dict>\n\t\t</dict>\n");
+    }
+    out.push_str("\t</array>\n</dict>\n</plist>\n");
+
+    out
+}
+
+/// The keys and values for all of the settings that are set
+fn settings_entries(settings: &ThemeSettings) -> Vec<(&'static str, String)> {
+    let ThemeSettings {
+        foreground,
+        background,
+        caret,
+        line_highlight,
+        misspelling,
+        minimap_border,
+        accent,
+        popup_css,
+        phantom_css,
+        bracket_contents_foreground,
+        bracket_contents_options,
+        brackets_foreground,
+        brackets_background,
+        brackets_options,
+        tags_foreground,
+        tags_options,
+        highlight,
+        find_highlight,
+        find_highlight_foreground,
+        gutter,
+        gutter_foreground,
+        selection,
+        selection_foreground,
+        selection_border,
+        inactive_selection,
+        inactive_selection_foreground,
+        guide,
+        active_guide,
+        stack_guide,
+        shadow,
+    } = settings;
+
+    let colors = [
+        ("foreground", foreground),
+        ("background", background),
+        ("caret", caret),
+        ("lineHighlight", line_highlight),
+        ("misspelling", misspelling),
+        ("minimapBorder", minimap_border),
+        ("accent", accent),
+        ("bracketContentsForeground", bracket_contents_foreground),
+        ("bracketsForeground", brackets_foreground),
+        ("bracketsBackground", brackets_background),
+        ("tagsForeground", tags_foreground),
+        ("highlight", highlight),
+        ("findHighlight", find_highlight),
+        ("findHighlightForeground", find_highlight_foreground),
+        ("gutter", gutter),
+        ("gutterForeground", gutter_foreground),
+        ("selection", selection),
+        ("selectionForeground", selection_foreground),
+        ("selectionBorder", selection_border),
+        ("inactiveSelection", inactive_selection),
+        ("inactiveSelectionForeground", inactive_selection_foreground),
+        ("guide", guide),
+        ("activeGuide", active_guide),
+        ("stackGuide", stack_guide),
+        ("shadow", shadow),
+    ];
+    let colors = colors
+        .into_iter()
+        .filter_map(|(key, color)| color.map(|color| (key, color_text(color))));
+    let css = [("popupCss", popup_css), ("phantomCss", phantom_css)]
+        .into_iter()
+        .filter_map(|(key, css)| css.clone().map(|css| (key, css)));
+    let options = [
+        ("bracketContentsOptions", bracket_contents_options),
+        ("bracketsOptions", brackets_options),
+        ("tagsOptions", tags_options),
+    ];
+    let options = options.into_iter().filter_map(|(key, option)| {
+        let option = match option.as_ref()? {
+            UnderlineOption::None => return None,
+            UnderlineOption::Underline => "underline",
+            UnderlineOption::StippledUnderline => "stippled_underline",
+            UnderlineOption::SquigglyUnderline => "squiggly_underline",
+        };
+        Some((key, option.to_owned()))
+    });
+
+    colors.chain(css).chain(options).collect()
+}
+
+fn write_entry(out: &mut String, indent: usize, key: &str, value: &str) {
+    let indent = "\t".repeat(indent);
+    write!(out, "{indent}<key>{key}</key>\n{indent}<string>").expect("Infallible");
+    crate::html::push_escaped(out, value);
+    out.push_str("</string>\n");
+}
+
+fn color_text(Color { r, g, b, a }: Color) -> String {
+    if a == 0xff {
+        format!("#{r:02X}{g:02X}{b:02X}")
+    } else {
+        format!("#{r:02X}{g:02X}{b:02X}{a:02X}")
+    }
+}
+
+fn font_style_text(font_style: FontStyle) -> String {
+    let words: Vec<_> = [
+        (FontStyle::BOLD, "bold"),
+        (FontStyle::ITALIC, "italic"),
+        (FontStyle::UNDERLINE, "underline"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| font_style.contains(*flag))
+    .map(|(_, word)| word)
+    .collect();
+    words.join(" ")
+}
+
+fn selectors_text(selectors: &ScopeSelectors) -> String {
+    let selectors: Vec<_> = selectors.selectors.iter().map(selector_text).collect();
+    selectors.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{io::Cursor, str::FromStr};
+
+    use strum::IntoEnumIterator;
+    use syntect::highlighting::{ThemeItem, ThemeSet};
+
+    use crate::theme::{extra, EmbeddedThemeName};
+
+    fn roundtrip(theme: &Theme) -> Theme {
+        let tmtheme = to_tmtheme(theme);
+        ThemeSet::load_from_reader(&mut Cursor::new(tmtheme)).unwrap()
+    }
+
+    #[test]
+    fn embedded_themes_roundtrip() {
+        let theme_set = extra();
+        let eq = EmbeddedThemeName::iter()
+            .map(|name| theme_set.get(name))
+            .all(|theme| roundtrip(theme) == *theme);
+        assert!(eq);
+    }
+
+    #[test]
+    fn handwritten_theme() {
+        let theme = Theme {
+            name: Some("<Tiny> & \"Friends\"".into()),
+            author: None,
+            settings: ThemeSettings {
+                background: Some(Color {
+                    r: 0x12,
+                    g: 0x34,
+                    b: 0x56,
+                    a: 0x78,
+                }),
+                popup_css: Some("html { color: red; }".into()),
+                brackets_options: Some(UnderlineOption::SquigglyUnderline),
+                ..ThemeSettings::default()
+            },
+            scopes: vec![ThemeItem {
+                scope: ScopeSelectors::from_str("source.c++ meta.block - string, comment").unwrap(),
+                style: StyleModifier {
+                    foreground: None,
+                    background: None,
+                    font_style: Some(FontStyle::empty()),
+                },
+            }],
+        };
+        assert_eq!(roundtrip(&theme), theme);
+        insta::assert_snapshot!(to_tmtheme(&theme), @r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+        <plist version="1.0">
+        <dict>
+        	<key>name</key>
+        	<string>&lt;Tiny&gt; &amp; &quot;Friends&quot;</string>
+        	<key>settings</key>
+        	<array>
+        		<dict>
+        			<key>settings</key>
+        			<dict>
+        				<key>background</key>
+        				<string>#12345678</string>
+        				<key>popupCss</key>
+        				<string>html { color: red; }</string>
+        				<key>bracketsOptions</key>
+        				<string>squiggly_underline</string>
+        			</dict>
+        		</dict>
+        		<dict>
+        			<key>scope</key>
+        			<string>source.c++ meta.block - string, comment</string>
+        			<key>settings</key>
+        			<dict>
+        				<key>fontStyle</key>
+        				<string></string>
+        			</dict>
+        		</dict>
+        	</array>
+        </dict>
+        </plist>
+        "#);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt::Write;
+
+use syntect::highlighting::{Color, ScopeSelector, Theme, ThemeSettings};
+
+use crate::terminal::{AnsiColor, ColorDepth, FontCapabilities};
+
+/// Quantizes all of the colors in a theme to fit within `depth`
+///
+/// The colors get encoded as palette indices in the same way as the terminal-palette themes (e.g.
+/// [`EmbeddedThemeName::Base16_256`][super::EmbeddedThemeName::Base16_256]), so the resulting
+/// theme should be displayed with [`crate::terminal::Renderer`]. See
+/// [`AnsiColor::downsample()`] for how colors get mapped
+///
+/// # Example
+///
+/// ```
+/// use two_face::{terminal::ColorDepth, theme::{extra, quantize, EmbeddedThemeName}};
+///
+/// let theme_set = extra();
+/// let nord_256 = quantize(&theme_set[EmbeddedThemeName::Nord], ColorDepth::Ansi256);
+/// // Nord's background color (`#2e3440`) is approximated by palette index 236
+/// let bg = nord_256.settings.background.unwrap();
+/// assert_eq!((bg.r, bg.a), (236, 0));
+/// ```
+pub fn quantize(theme: &Theme, depth: ColorDepth) -> Theme {
+    map_colors(theme, |color| {
+        AnsiColor::from(color).downsample(depth).into()
+    })
+}
+
+/// Downgrades any font styles in a theme that aren't supported by `capabilities`
+///
+/// This lets the same theme be displayed on terminals with varying font support without
+/// maintaining separate copies. See [`FontCapabilities::apply_modifier()`] for the details
+///
+/// # Example
+///
+/// ```
+/// use two_face::{
+///     terminal::FontCapabilities,
+///     theme::{downgrade_font_styles, extra, EmbeddedThemeName},
+/// };
+/// use syntect::highlighting::FontStyle;
+///
+/// let theme_set = extra();
+/// let no_italics = FontCapabilities::new(FontStyle::BOLD | FontStyle::UNDERLINE);
+/// let theme = downgrade_font_styles(&theme_set[EmbeddedThemeName::Nord], &no_italics);
+/// assert!(theme
+///     .scopes
+///     .iter()
+///     .filter_map(|item| item.style.font_style)
+///     .all(|font_style| !font_style.contains(FontStyle::ITALIC)));
+/// ```
+pub fn downgrade_font_styles(theme: &Theme, capabilities: &FontCapabilities) -> Theme {
+    let mut theme = theme.clone();
+    for item in &mut theme.scopes {
+        item.style = capabilities.apply_modifier(item.style);
+    }
+    theme
+}
+
+/// Strips a theme's background so that the terminal's own background shows through
+///
+/// The background gets set to the terminal's default color (see [`crate::terminal`] for how
+/// that's encoded), and any scope that only re-applied the old background gets it removed.
+/// `line_highlight` and `gutter` are cleared too since they're picked to contrast with the old
+/// background, so they'd look out of place over an arbitrary one. Scopes with their own distinct
+/// background (e.g. `invalid`) keep it, which matches how [`crate::terminal::Renderer`] treats
+/// backgrounds by default
+///
+/// # Example
+///
+/// ```
+/// use two_face::{
+///     terminal::AnsiColor,
+///     theme::{extra, without_background, EmbeddedThemeName},
+/// };
+///
+/// let theme_set = extra();
+/// let theme = without_background(&theme_set[EmbeddedThemeName::Dracula]);
+/// let bg = theme.settings.background.unwrap();
+/// assert_eq!(AnsiColor::from(bg), AnsiColor::Default);
+/// assert_eq!(theme.settings.gutter, None);
+/// ```
+pub fn without_background(theme: &Theme) -> Theme {
+    let mut theme = theme.clone();
+    let old_background = theme.settings.background;
+    theme.settings.background = Some(AnsiColor::Default.into());
+    theme.settings.line_highlight = None;
+    theme.settings.gutter = None;
+    for item in &mut theme.scopes {
+        if item.style.background.is_some() && item.style.background == old_background {
+            item.style.background = None;
+        }
+    }
+    theme
+}
+
+/// Applies `f` to every color within the theme
+pub(crate) fn map_colors(theme: &Theme, mut f: impl FnMut(Color) -> Color) -> Theme {
+    let mut theme = theme.clone();
+    for color in settings_colors(&mut theme.settings) {
+        *color = color.map(&mut f);
+    }
+    for item in &mut theme.scopes {
+        let style = &mut item.style;
+        style.foreground = style.foreground.map(&mut f);
+        style.background = style.background.map(&mut f);
+    }
+    theme
+}
+
+/// All of the colors held in a theme's settings
+pub(crate) fn settings_colors(settings: &mut ThemeSettings) -> [&mut Option<Color>; 25] {
+    let ThemeSettings {
+        foreground,
+        background,
+        caret,
+        line_highlight,
+        misspelling,
+        minimap_border,
+        accent,
+        popup_css: _,
+        phantom_css: _,
+        bracket_contents_foreground,
+        bracket_contents_options: _,
+        brackets_foreground,
+        brackets_background,
+        brackets_options: _,
+        tags_foreground,
+        tags_options: _,
+        highlight,
+        find_highlight,
+        find_highlight_foreground,
+        gutter,
+        gutter_foreground,
+        selection,
+        selection_foreground,
+        selection_border,
+        inactive_selection,
+        inactive_selection_foreground,
+        guide,
+        active_guide,
+        stack_guide,
+        shadow,
+    } = settings;
+    [
+        foreground,
+        background,
+        caret,
+        line_highlight,
+        misspelling,
+        minimap_border,
+        accent,
+        bracket_contents_foreground,
+        brackets_foreground,
+        brackets_background,
+        tags_foreground,
+        highlight,
+        find_highlight,
+        find_highlight_foreground,
+        gutter,
+        gutter_foreground,
+        selection,
+        selection_foreground,
+        selection_border,
+        inactive_selection,
+        inactive_selection_foreground,
+        guide,
+        active_guide,
+        stack_guide,
+        shadow,
+    ]
+}
+
+/// Formats a scope selector the way it's written in themes (e.g. `source.rust - comment`)
+pub(crate) fn selector_text(selector: &ScopeSelector) -> String {
+    let mut text = selector.path.to_string().trim_end().to_owned();
+    for exclude in &selector.excludes {
+        write!(text, " - {}", exclude.to_string().trim_end()).expect("Infallible");
+    }
+    text
+}
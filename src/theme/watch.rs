@@ -0,0 +1,369 @@
+//! Hot-reloading a folder of `.tmTheme` files
+//!
+//! A [`ThemeWatcher`] keeps an up-to-date [`LazyThemeSet`] snapshot of a folder's themes while
+//! reporting each change through a channel. Themes that fail to load keep their last good version
+//! in the snapshot, so a half-saved file doesn't knock a theme out from under a running previewer
+//!
+//! # Example
+//!
+//! ```no_run
+//! use two_face::theme::watch::{ThemeWatcher, WatchEvent};
+//!
+//! let watcher = ThemeWatcher::new("themes")?;
+//! for event in watcher.events() {
+//!     match event {
+//!         WatchEvent::Updated(name) => {
+//!             let snapshot = watcher.snapshot();
+//!             let theme = snapshot.get(&name).unwrap();
+//!             // Re-render the preview with the new theme
+//!         }
+//!         WatchEvent::Failed { path, error } => eprintln!("{}: {error}", path.display()),
+//!         _ => {}
+//!     }
+//! }
+//! # Ok::<_, two_face::theme::watch::Error>(())
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, RwLock},
+    thread,
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use syntect::{highlighting::ThemeSet, LoadingError};
+
+use super::{
+    core_types::LazyTheme,
+    load::{theme_name, tm_theme_paths},
+    LazyThemeSet,
+};
+
+/// An error from setting up a [`ThemeWatcher`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Failed reading the folder's initial contents
+    Io(io::Error),
+    /// Failed watching the folder
+    Notify(notify::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed reading themes: {err}"),
+            Self::Notify(err) => write!(f, "failed watching themes: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Notify(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(err: notify::Error) -> Self {
+        Self::Notify(err)
+    }
+}
+
+/// A change to the watched themes
+///
+/// Each event is sent after the new snapshot is published
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WatchEvent {
+    /// A theme was added or changed
+    Updated(String),
+    /// A theme's file was removed
+    Removed(String),
+    /// A theme failed to load, so its last good version (if any) is kept
+    Failed { path: PathBuf, error: LoadingError },
+    /// The underlying watcher ran into an error
+    Watch(notify::Error),
+}
+
+/// Watches a folder (including nested folders) of `.tmTheme` files
+///
+/// Themes are named after their file stems like with [`LazyThemeSet::load_from_folder()`]. When
+/// several files share a file stem, the one whose path sorts last is used. Nested folders that get
+/// moved in, moved out, or removed have all of their themes updated at once. The folder stops being
+/// watched when the watcher is dropped
+pub struct ThemeWatcher {
+    snapshot: Arc<RwLock<Arc<LazyThemeSet>>>,
+    events: mpsc::Receiver<WatchEvent>,
+    // Only held on to so that it keeps running. Dropping it also stops the thread that handles
+    // its changes
+    _watcher: RecommendedWatcher,
+}
+
+impl ThemeWatcher {
+    /// Loads all of the themes in `folder` and starts watching it for changes
+    ///
+    /// Only the initial themes that fail to load are reported through [`ThemeWatcher::events()`]
+    pub fn new<P: AsRef<Path>>(folder: P) -> Result<Self, Error> {
+        let folder = folder.as_ref();
+        // Start watching before the initial scan, so that changes made during it are buffered
+        // instead of missed
+        let (change_sender, changes) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                let _ = change_sender.send(res);
+            })?;
+        watcher.watch(folder, RecursiveMode::Recursive)?;
+
+        let (sender, events) = mpsc::channel();
+        let mut themes = Themes::default();
+        for path in tm_theme_paths(folder)? {
+            if let Some(event @ WatchEvent::Failed { .. }) = themes.reload(&path) {
+                let _ = sender.send(event);
+            }
+        }
+        let snapshot = Arc::new(RwLock::new(Arc::new(themes.snapshot())));
+
+        let shared = Arc::clone(&snapshot);
+        thread::spawn(move || {
+            for res in changes {
+                let event = match res {
+                    Ok(event) => event,
+                    Err(err) => {
+                        let _ = sender.send(WatchEvent::Watch(err));
+                        continue;
+                    }
+                };
+                if matches!(event.kind, EventKind::Access(_)) {
+                    continue;
+                }
+                for path in &event.paths {
+                    let events = if path.extension() == Some(OsStr::new("tmTheme")) {
+                        themes.reload(path).into_iter().collect()
+                    } else {
+                        themes.reload_folder(path)
+                    };
+                    if events
+                        .iter()
+                        .any(|event| !matches!(event, WatchEvent::Failed { .. }))
+                    {
+                        *shared.write().unwrap() = Arc::new(themes.snapshot());
+                    }
+                    for event in events {
+                        let _ = sender.send(event);
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            snapshot,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// The latest version of the watched themes
+    ///
+    /// Snapshots are cheap to hold on to and don't change, so grab a new one after each event
+    pub fn snapshot(&self) -> Arc<LazyThemeSet> {
+        Arc::clone(&self.snapshot.read().unwrap())
+    }
+
+    /// Changes to the watched themes
+    pub fn events(&self) -> &mpsc::Receiver<WatchEvent> {
+        &self.events
+    }
+}
+
+impl fmt::Debug for ThemeWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThemeWatcher")
+            .field("snapshot", &self.snapshot)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The last good version of each theme keyed by its path
+///
+/// Themes are kept in their serialized form, so building a snapshot doesn't have to clone any
+/// parsed themes
+#[derive(Default)]
+struct Themes(BTreeMap<PathBuf, LazyTheme>);
+
+impl Themes {
+    /// Updates the theme at `path`, returning the matching event if anything changed
+    fn reload(&mut self, path: &Path) -> Option<WatchEvent> {
+        let name = theme_name(path)?;
+        if !path.exists() {
+            self.0.remove(path)?;
+            // Another file with the same stem takes over the name
+            let replaced = self
+                .0
+                .keys()
+                .any(|other| theme_name(other).as_ref() == Some(&name));
+            return Some(if replaced {
+                WatchEvent::Updated(name)
+            } else {
+                WatchEvent::Removed(name)
+            });
+        }
+
+        match ThemeSet::get_theme(path) {
+            Ok(theme) => {
+                self.0.insert(path.to_owned(), LazyTheme::from(&theme));
+                Some(WatchEvent::Updated(name))
+            }
+            // The file was removed before we could read it
+            Err(LoadingError::Io(err)) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => Some(WatchEvent::Failed {
+                path: path.to_owned(),
+                error,
+            }),
+        }
+    }
+
+    /// Rescans a folder that was added, removed, or renamed, returning the events for its themes
+    ///
+    /// Only the folder itself gets reported in these cases, so its themes have to be found here
+    fn reload_folder(&mut self, folder: &Path) -> Vec<WatchEvent> {
+        let mut paths: BTreeSet<PathBuf> = self
+            .0
+            .keys()
+            .filter(|path| path.starts_with(folder))
+            .cloned()
+            .collect();
+        if folder.is_dir() {
+            // A folder that can't be read only has its known themes reloaded
+            paths.extend(tm_theme_paths(folder).unwrap_or_default());
+        }
+        paths.iter().filter_map(|path| self.reload(path)).collect()
+    }
+
+    fn snapshot(&self) -> LazyThemeSet {
+        // Later paths overwrite earlier ones that share a name
+        let themes = self
+            .0
+            .iter()
+            .filter_map(|(path, theme)| Some((theme_name(path)?, theme.clone())))
+            .collect();
+        LazyThemeSet { themes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use super::*;
+
+    use crate::theme::{extra, to_tmtheme, EmbeddedThemeName};
+
+    /// Skips over the duplicate events that a single write can trigger
+    fn next_event(watcher: &ThemeWatcher, is_match: impl Fn(&WatchEvent) -> bool) -> WatchEvent {
+        loop {
+            let event = watcher
+                .events()
+                .recv_timeout(Duration::from_secs(10))
+                .expect("Timed out waiting for an event");
+            if is_match(&event) {
+                return event;
+            }
+        }
+    }
+
+    #[test]
+    fn hot_reload() {
+        let embedded = extra();
+        let nord = embedded.get(EmbeddedThemeName::Nord);
+        let dracula = embedded.get(EmbeddedThemeName::Dracula);
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let path = dir.join("Preview.tmTheme");
+        fs::write(&path, to_tmtheme(nord)).unwrap();
+        fs::write(dir.join("Broken.tmTheme"), "<plist>").unwrap();
+        // Files that share a stem don't clobber each other
+        let nested = dir.join("nested");
+        fs::create_dir(&nested).unwrap();
+        fs::write(dir.join("Other.tmTheme"), to_tmtheme(nord)).unwrap();
+        fs::write(nested.join("Other.tmTheme"), to_tmtheme(dracula)).unwrap();
+
+        let watcher = ThemeWatcher::new(dir).unwrap();
+        let event = next_event(&watcher, |_| true);
+        assert!(matches!(event, WatchEvent::Failed { .. }), "{event:?}");
+        let first = watcher.snapshot();
+        assert_eq!(first.get("Preview"), Some(nord));
+        assert!(!first.contains("Broken"));
+        assert_eq!(first.get("Other"), Some(dracula));
+
+        fs::write(&path, to_tmtheme(dracula)).unwrap();
+        next_event(&watcher, |event| matches!(event, WatchEvent::Updated(_)));
+        assert_eq!(watcher.snapshot().get("Preview"), Some(dracula));
+        // Old snapshots stay as they were
+        assert_eq!(first.get("Preview"), Some(nord));
+
+        // Broken edits keep the last good version around
+        fs::write(&path, "<plist><dict>").unwrap();
+        next_event(&watcher, |event| matches!(event, WatchEvent::Failed { .. }));
+        assert_eq!(watcher.snapshot().get("Preview"), Some(dracula));
+
+        fs::remove_file(&path).unwrap();
+        let event = next_event(&watcher, |event| matches!(event, WatchEvent::Removed(_)));
+        assert!(matches!(event, WatchEvent::Removed(name) if name == "Preview"));
+        assert!(!watcher.snapshot().contains("Preview"));
+
+        fs::remove_file(nested.join("Other.tmTheme")).unwrap();
+        next_event(
+            &watcher,
+            |event| matches!(event, WatchEvent::Updated(name) if name == "Other"),
+        );
+        assert_eq!(watcher.snapshot().get("Other"), Some(nord));
+    }
+
+    #[test]
+    fn moved_folders() {
+        let zenburn = extra().get(EmbeddedThemeName::Zenburn).clone();
+        let parent = tempfile::tempdir().unwrap();
+        let parent = parent.path();
+        let dir = parent.join("themes");
+        let outside = parent.join("outside");
+        fs::create_dir(&dir).unwrap();
+        fs::create_dir_all(outside.join("deeper")).unwrap();
+        fs::write(
+            outside.join("deeper").join("Zen.tmTheme"),
+            to_tmtheme(&zenburn),
+        )
+        .unwrap();
+
+        let watcher = ThemeWatcher::new(&dir).unwrap();
+        assert!(!watcher.snapshot().contains("Zen"));
+
+        // Only the folder gets reported when it's moved in or out
+        let moved = dir.join("moved");
+        fs::rename(&outside, &moved).unwrap();
+        next_event(
+            &watcher,
+            |event| matches!(event, WatchEvent::Updated(name) if name == "Zen"),
+        );
+        assert_eq!(watcher.snapshot().get("Zen"), Some(&zenburn));
+
+        fs::rename(&moved, &outside).unwrap();
+        next_event(
+            &watcher,
+            |event| matches!(event, WatchEvent::Removed(name) if name == "Zen"),
+        );
+        assert!(!watcher.snapshot().contains("Zen"));
+    }
+}
@@ -0,0 +1,28 @@
+//! Snapshots of the generated stylesheets, so that any changes to them get reviewed
+
+use syntect::html::ClassStyle;
+use two_face::theme::{css, css_all_themes, EmbeddedLazyThemeSet};
+
+#[test]
+fn every_embedded_theme() {
+    for &name in EmbeddedLazyThemeSet::theme_names() {
+        insta::assert_snapshot!(name.as_slug(), css(name, ClassStyle::Spaced));
+    }
+}
+
+#[test]
+fn all_themes_are_scoped() {
+    let prefix = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+    let stylesheet = css_all_themes(prefix);
+    for &name in EmbeddedLazyThemeSet::theme_names() {
+        let container = format!("[data-theme=\"{}\"] .hl-code {{\n", name.as_slug());
+        assert!(stylesheet.contains(&container), "{name}");
+    }
+    for line in stylesheet.lines().filter(|line| line.ends_with('{')) {
+        assert!(line.starts_with("[data-theme=\""), "{line}");
+        assert_eq!(
+            line.matches("[data-theme=").count(),
+            line.matches(", ").count() + 1
+        );
+    }
+}
@@ -4,5 +4,9 @@
 //! included within here
 
 mod acknowledgements;
+mod css;
 mod docs_watchdog;
+#[cfg(feature = "import")]
+mod sublime_color_scheme;
+mod terminal_scheme;
 mod utils;
@@ -0,0 +1,212 @@
+//! Resolving the colors and structure of `.sublime-color-scheme`s
+
+use std::fmt::Write;
+
+use two_face::theme::{import::sublime, to_tmtheme};
+
+#[test]
+fn color_expressions() {
+    const EXPRESSIONS: &[&str] = &[
+        "#abc",
+        "#12345678",
+        "RebeccaPurple",
+        "transparent",
+        "rgb(255, 0, 0)",
+        "rgba(0 128 255 / 50%)",
+        "rgb(100%, 50%, 0%)",
+        "hsl(120, 100%, 25%)",
+        "hsla(210deg 50% 60% / 0.5)",
+        "hwb(0 20% 20%)",
+        "hwb(0 60% 60%)",
+        "var(blue)",
+        "var(alias)",
+        "color(var(blue) alpha(0.5))",
+        "color(var(blue) a(25%))",
+        "color(var(blue) alpha(0.5) alpha(* 0.5))",
+        "color(var(blue) a(- 0.25))",
+        "color(var(blue) l(+ 10%))",
+        "color(var(blue) lightness(20%))",
+        "color(var(blue) s(- 50%))",
+        "color(var(blue) saturation(0%))",
+        "color(var(blue) blend(white 25%))",
+        "color(var(blue) blend(#ffffff00 50%))",
+        "color(var(blue) blenda(#ffffff00 50%))",
+        "color(var(blue) min-contrast(var(bg) 4.5))",
+        "color(var(bg) min-contrast(white 7))",
+        "color(color(var(blue) alpha(0.5)) l(20%))",
+        "var(loop)",
+        "var(missing)",
+        "color(var(blue) unknown(1))",
+        "rgb(1, 2)",
+        "nonsense",
+    ];
+
+    let rules: Vec<_> = EXPRESSIONS
+        .iter()
+        .enumerate()
+        .map(|(i, expr)| format!(r#"{{ "scope": "expr.{i}", "foreground": "{expr}" }}"#))
+        .collect();
+    let scheme = format!(
+        r##"{{
+            "variables": {{
+                "blue": "hsl(210, 50%, 50%)",
+                "alias": "var(blue)",
+                "bg": "#303030",
+                "loop": "var(loop)",
+            }},
+            "rules": [{}],
+        }}"##,
+        rules.join(",\n")
+    );
+    let theme = sublime::parse(&scheme).unwrap();
+
+    let mut resolved = String::new();
+    for (i, expr) in EXPRESSIONS.iter().enumerate() {
+        let item = theme
+            .scopes
+            .iter()
+            .find(|item| item.scope.selectors[0].path.to_string().trim() == format!("expr.{i}"))
+            .unwrap();
+        let color = match item.style.foreground {
+            Some(c) => format!("#{:02x}{:02x}{:02x}{:02x}", c.r, c.g, c.b, c.a),
+            None => "-".to_owned(),
+        };
+        writeln!(resolved, "{expr} => {color}").unwrap();
+    }
+    insta::assert_snapshot!(resolved, @r"
+    #abc => #aabbccff
+    #12345678 => #12345678
+    RebeccaPurple => #663399ff
+    transparent => #00000000
+    rgb(255, 0, 0) => #ff0000ff
+    rgba(0 128 255 / 50%) => #0080ff80
+    rgb(100%, 50%, 0%) => #ff8000ff
+    hsl(120, 100%, 25%) => #008000ff
+    hsla(210deg 50% 60% / 0.5) => #6699cc80
+    hwb(0 20% 20%) => #cc3333ff
+    hwb(0 60% 60%) => #808080ff
+    var(blue) => #4080bfff
+    var(alias) => #4080bfff
+    color(var(blue) alpha(0.5)) => #4080bf80
+    color(var(blue) a(25%)) => #4080bf40
+    color(var(blue) alpha(0.5) alpha(* 0.5)) => #4080bf40
+    color(var(blue) a(- 0.25)) => #4080bfbf
+    color(var(blue) l(+ 10%)) => #6699ccff
+    color(var(blue) lightness(20%)) => #1a334dff
+    color(var(blue) s(- 50%)) => #808080ff
+    color(var(blue) saturation(0%)) => #808080ff
+    color(var(blue) blend(white 25%)) => #cfdfefff
+    color(var(blue) blend(#ffffff00 50%)) => #9fbfdfff
+    color(var(blue) blenda(#ffffff00 50%)) => #9fbfdf80
+    color(var(blue) min-contrast(var(bg) 4.5)) => #6a9ccdff
+    color(var(bg) min-contrast(white 7)) => #303030ff
+    color(color(var(blue) alpha(0.5)) l(20%)) => #1a334d80
+    var(loop) => -
+    var(missing) => -
+    color(var(blue) unknown(1)) => -
+    rgb(1, 2) => -
+    nonsense => -
+    ");
+}
+
+#[test]
+fn full_scheme() {
+    let scheme = r##"// Sublime allows comments and trailing commas too
+{
+    "name": "Tiny",
+    "author": "Someone",
+    "variables": {
+        "black": "hsl(0, 0%, 10%)",
+        "white": "hsl(0, 0%, 90%)",
+        "orange": "#f99157",
+    },
+    "globals": {
+        "foreground": "var(white)",
+        "background": "var(black)",
+        "line_highlight": "color(var(white) alpha(0.05))",
+        "gutter_foreground": "color(var(white) blend(var(black) 50%))",
+        "brackets_options": "foreground squiggly_underline",
+        "popup_css": "html { color: var(--foreground); }",
+        "selection_corner_style": "round",
+    },
+    "rules": [
+        {
+            "name": "Keywords",
+            "scope": "keyword, storage - storage.type",
+            "foreground": "var(orange)",
+            "font_style": "bold italic glow",
+        },
+        {
+            "scope": "source variable",
+            "foreground": ["var(orange)", "var(white)"],
+            "background": "color(var(orange) a(0.1))",
+        },
+        { "scope": "markup.underline", "font_style": "squiggly_underline" },
+        { "foreground": "var(orange)" },
+    ],
+}"##;
+    let theme = sublime::parse(scheme).unwrap();
+    insta::assert_snapshot!(to_tmtheme(&theme), @r#"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+    <plist version="1.0">
+    <dict>
+    	<key>name</key>
+    	<string>Tiny</string>
+    	<key>author</key>
+    	<string>Someone</string>
+    	<key>settings</key>
+    	<array>
+    		<dict>
+    			<key>settings</key>
+    			<dict>
+    				<key>foreground</key>
+    				<string>#E6E6E6</string>
+    				<key>background</key>
+    				<string>#1A1A1A</string>
+    				<key>lineHighlight</key>
+    				<string>#E6E6E60D</string>
+    				<key>gutterForeground</key>
+    				<string>#808080</string>
+    				<key>popupCss</key>
+    				<string>html { color: var(--foreground); }</string>
+    				<key>bracketsOptions</key>
+    				<string>squiggly_underline</string>
+    			</dict>
+    		</dict>
+    		<dict>
+    			<key>scope</key>
+    			<string>keyword, storage - storage.type</string>
+    			<key>settings</key>
+    			<dict>
+    				<key>foreground</key>
+    				<string>#F99157</string>
+    				<key>fontStyle</key>
+    				<string>bold italic</string>
+    			</dict>
+    		</dict>
+    		<dict>
+    			<key>scope</key>
+    			<string>source variable</string>
+    			<key>settings</key>
+    			<dict>
+    				<key>foreground</key>
+    				<string>#F99157</string>
+    				<key>background</key>
+    				<string>#F991571A</string>
+    			</dict>
+    		</dict>
+    		<dict>
+    			<key>scope</key>
+    			<string>markup.underline</string>
+    			<key>settings</key>
+    			<dict>
+    				<key>fontStyle</key>
+    				<string>underline</string>
+    			</dict>
+    		</dict>
+    	</array>
+    </dict>
+    </plist>
+    "#);
+}
@@ -0,0 +1,55 @@
+//! Snapshots of the terminal color schemes derived from the embedded themes
+
+use two_face::theme::{extra, EmbeddedLazyThemeSet, EmbeddedThemeName, TerminalScheme};
+
+#[test]
+fn every_embedded_theme() {
+    let theme_set = extra();
+    for &name in EmbeddedLazyThemeSet::theme_names() {
+        let scheme = TerminalScheme::from_theme(&theme_set[name]);
+        let formats = [
+            ("alacritty.toml", scheme.alacritty()),
+            ("kitty.conf", scheme.kitty()),
+            ("windows-terminal.json", scheme.windows_terminal()),
+            ("foot.ini", scheme.foot()),
+            ("Xresources", scheme.xresources()),
+        ];
+        let all = formats
+            .iter()
+            .map(|(file, contents)| format!("==> {file} <==\n{contents}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!(name.as_slug(), all);
+    }
+}
+
+#[test]
+fn windows_terminal() {
+    let theme_set = extra();
+    let scheme = TerminalScheme::from_theme(&theme_set[EmbeddedThemeName::Dracula]);
+    insta::assert_snapshot!(scheme.windows_terminal(), @r##"
+    {
+        "name": "Dracula",
+        "foreground": "#f8f8f2",
+        "background": "#282a36",
+        "cursorColor": "#f8f8f0",
+        "selectionBackground": "#44475a",
+        "black": "#474952",
+        "red": "#f83333",
+        "green": "#50fa7b",
+        "yellow": "#f1fa8c",
+        "blue": "#6272a4",
+        "purple": "#ff79c6",
+        "cyan": "#66d9ef",
+        "white": "#f8f8f2",
+        "brightBlack": "#7b7c81",
+        "brightRed": "#fa6666",
+        "brightGreen": "#7cfb9c",
+        "brightYellow": "#f5fba9",
+        "brightBlue": "#8995bb",
+        "brightPurple": "#ff9bd4",
+        "brightCyan": "#8ce3f3",
+        "brightWhite": "#fafaf6"
+    }
+    "##);
+}
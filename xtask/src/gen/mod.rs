@@ -7,6 +7,7 @@ use syntect::{
     parsing::{SyntaxSet, SyntaxSetBuilder},
 };
 use tempfile::TempDir;
+use two_face::theme::import;
 use xshell::{cmd, Shell};
 
 use self::acknowledgements::{Acknowledgements, License, NORM_LICENSE_STEMS};
@@ -15,7 +16,7 @@ mod acknowledgements;
 mod themes;
 mod utils;
 
-const NORM_RELEVANT_EXTS: &[&str] = &["patch", "sublime-syntax", "tmtheme"];
+const NORM_RELEVANT_EXTS: &[&str] = &["patch", "sublime-color-scheme", "sublime-syntax", "tmtheme"];
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum RegexImpl {
@@ -145,6 +146,21 @@ impl AssetsDir {
         let theme_dir = self.tempdir.path().join("themes");
         anyhow::ensure!(theme_dir.is_dir(), "Can't find themes dir at {theme_dir:?}",);
         let mut theme_set = ThemeSet::load_from_folder(&theme_dir)?;
+        // `ThemeSet` only picks up tmTheme files, so color schemes get added separately
+        for file in utils::walk_files(&theme_dir)? {
+            if file.extension().and_then(OsStr::to_str) != Some("sublime-color-scheme") {
+                continue;
+            }
+            let Some(name) = file.file_stem().and_then(OsStr::to_str) else {
+                continue;
+            };
+            match import::sublime::load_from_file(&file) {
+                Ok(theme) => {
+                    theme_set.themes.entry(name.to_owned()).or_insert(theme);
+                }
+                Err(err) => log::warn!("Failed loading color scheme from file. Skipping...\n{err}"),
+            }
+        }
 
         let mut full_set = ThemeSet::load_defaults();
         full_set.themes.append(&mut theme_set.themes);